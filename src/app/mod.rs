@@ -1 +1 @@
-mod web;
+pub mod web;
@@ -1 +1,395 @@
+//! HTTP and WebSocket front-end that shares a single [`Ecam`] connection between multiple clients.
 
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::Extension;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use tokio_stream::StreamExt;
+
+use longshot::ecam::{Ecam, EcamError, EcamStatus, EcamStatusEvent};
+use longshot::operations::*;
+use longshot::protocol::*;
+
+struct WebState {
+    ecam: Ecam,
+    /// Serializes the handlers' multi-request sequences (ie: selecting a profile and then saving a recipe to it) so
+    /// that concurrent requests can't interleave with them. Single requests are already serialized by [`Ecam`].
+    operation: Mutex<()>,
+}
+
+type SharedState = Arc<WebState>;
+
+/// An error returned from a handler, rendered as a JSON body with the given status code. The body always has an
+/// `error` message, along with any details that let clients react to the error (ie: the machine's state).
+struct WebError(StatusCode, Value);
+
+impl WebError {
+    fn bad_request(s: impl Into<String>) -> Self {
+        WebError(StatusCode::BAD_REQUEST, json!({ "error": s.into() }))
+    }
+}
+
+impl From<EcamError> for WebError {
+    fn from(e: EcamError) -> Self {
+        let mut body = json!({ "error": e.to_string() });
+        let status = match &e {
+            EcamError::NotFound => StatusCode::NOT_FOUND,
+            EcamError::IngredientCheck(err) => {
+                body["problems"] = json!(err.messages());
+                StatusCode::BAD_REQUEST
+            }
+            EcamError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+            EcamError::WrongState(status) => {
+                body["status"] = status_json(*status);
+                StatusCode::CONFLICT
+            }
+            EcamError::Alarm(alarm) => {
+                body["alarm"] = json!(format!("{:?}", alarm));
+                StatusCode::CONFLICT
+            }
+            EcamError::Timeout(status) => {
+                body["status"] = status.map_or(Value::Null, status_json);
+                StatusCode::GATEWAY_TIMEOUT
+            }
+            EcamError::Disconnected => StatusCode::SERVICE_UNAVAILABLE,
+            EcamError::Decode(_) | EcamError::NotAccepted(_) => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        WebError(status, body)
+    }
+}
+
+impl IntoResponse for WebError {
+    fn into_response(self) -> axum::response::Response {
+        (self.0, Json(self.1)).into_response()
+    }
+}
+
+/// Serves the REST and WebSocket API for the given [`Ecam`] until the server fails.
+pub async fn serve(ecam: Ecam, addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+    longshot::info!("Listening on http://{}", addr);
+    axum::Server::bind(&addr)
+        .serve(router(ecam).into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// Builds the routes for the REST and WebSocket API.
+fn router(ecam: Ecam) -> Router {
+    let state = Arc::new(WebState {
+        ecam,
+        operation: Mutex::new(()),
+    });
+    Router::new()
+        .route("/status", get(status))
+        .route("/recipes", get(recipes))
+        .route("/brew", post(brew_beverage))
+        .route("/save-recipe", post(save_beverage_recipe))
+        .route("/stop", post(stop_beverage))
+        .route("/turn-on", post(turn_on))
+        .route("/turn-off", post(turn_off))
+        .route("/ws", get(status_socket))
+        .layer(Extension(state))
+}
+
+fn status_json(status: EcamStatus) -> Value {
+    match status {
+        EcamStatus::StandBy => json!({ "state": "standby" }),
+        EcamStatus::TurningOn(percentage) => {
+            json!({ "state": "turning-on", "percentage": percentage })
+        }
+        EcamStatus::ShuttingDown(percentage) => {
+            json!({ "state": "shutting-down", "percentage": percentage })
+        }
+        EcamStatus::Ready => json!({ "state": "ready" }),
+        EcamStatus::Busy(percentage) => json!({ "state": "busy", "percentage": percentage }),
+        EcamStatus::Cleaning(percentage) => {
+            json!({ "state": "cleaning", "percentage": percentage })
+        }
+        EcamStatus::Descaling => json!({ "state": "descaling" }),
+        EcamStatus::Alarm(alarm) => json!({ "state": "alarm", "alarm": format!("{:?}", alarm) }),
+        EcamStatus::Fetching(percentage) => {
+            json!({ "state": "fetching", "percentage": percentage })
+        }
+    }
+}
+
+fn ingredient_json(ingredient: &IngredientRangeInfo) -> Option<Value> {
+    let range = |name: &str, min, value, max| json!({ "ingredient": name, "min": min, "default": value, "max": max });
+    match ingredient {
+        IngredientRangeInfo::Coffee(min, value, max) => Some(range("coffee", min, value, max)),
+        IngredientRangeInfo::Milk(min, value, max) => Some(range("milk", min, value, max)),
+        IngredientRangeInfo::HotWater(min, value, max) => Some(range("hotwater", min, value, max)),
+        IngredientRangeInfo::Taste(value) => {
+            Some(json!({ "ingredient": "taste", "default": value.to_arg_string() }))
+        }
+        IngredientRangeInfo::Temperature(value) => {
+            Some(json!({ "ingredient": "temperature", "default": value.to_arg_string() }))
+        }
+        // We don't support these for now
+        IngredientRangeInfo::Accessory(..)
+        | IngredientRangeInfo::Inversion(..)
+        | IngredientRangeInfo::Brew2(..) => None,
+    }
+}
+
+async fn status(Extension(state): Extension<SharedState>) -> Result<Json<Value>, WebError> {
+    Ok(Json(status_json(state.ecam.current_state().await?)))
+}
+
+async fn recipes(Extension(state): Extension<SharedState>) -> Result<Json<Value>, WebError> {
+    let lock = state.operation.lock().await;
+    let list = list_recipies_for(state.ecam.clone(), None).await?;
+    let names = read_custom_recipe_names(state.ecam.clone(), &list).await;
+    drop(lock);
+    let recipes = list
+        .recipes
+        .iter()
+        .map(|recipe| {
+            let mut json = json!({
+                "beverage": recipe.beverage.to_arg_string(),
+                "ingredients": recipe
+                    .fetch_ingredients()
+                    .iter()
+                    .filter_map(ingredient_json)
+                    .collect::<Vec<_>>(),
+            });
+            if let Some((_, name)) = names.iter().find(|(id, _)| *id == recipe.beverage) {
+                json["name"] = name.name().into();
+                json["icon"] = name.icon().into();
+            }
+            json
+        })
+        .collect::<Vec<_>>();
+    Ok(Json(Value::Array(recipes)))
+}
+
+/// Parses the beverage name, ingredients and check mode from a request body that mirrors the `brew` command-line arguments.
+fn parse_beverage_request(
+    body: &Value,
+) -> Result<(&str, Vec<BrewIngredientInfo>, IngredientCheckMode), WebError> {
+    let beverage = body
+        .get("beverage")
+        .and_then(Value::as_str)
+        .ok_or_else(|| WebError::bad_request("Missing beverage"))?;
+
+    let mut ingredients = vec![];
+    for arg in ["coffee", "milk", "hotwater", "taste", "temperature"] {
+        if let Some(value) = body.get(arg) {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                value => value.to_string(),
+            };
+            let ingredient = BrewIngredientInfo::from_arg(arg, &value).ok_or_else(|| {
+                WebError::bad_request(format!("Invalid value '{}' for '{}'", value, arg))
+            })?;
+            ingredients.push(ingredient);
+        }
+    }
+
+    let flag = |name| body.get(name).and_then(Value::as_bool).unwrap_or_default();
+    let mode = match (flag("allow_defaults"), flag("force")) {
+        (_, true) => IngredientCheckMode::Force,
+        (true, false) => IngredientCheckMode::AllowDefaults,
+        (false, false) => IngredientCheckMode::Strict,
+    };
+    Ok((beverage, ingredients, mode))
+}
+
+/// Resolves the optional `profile` of a request body, which may be either a profile number or name.
+async fn parse_profile(ecam: &Ecam, body: &Value) -> Result<u8, WebError> {
+    Ok(match body.get("profile") {
+        Some(Value::String(profile)) => resolve_profile(ecam.clone(), profile).await?,
+        Some(Value::Number(profile)) => resolve_profile(ecam.clone(), &profile.to_string()).await?,
+        _ => DEFAULT_PROFILE,
+    })
+}
+
+/// Validates the requested beverage and starts brewing it. The request body mirrors the `brew` command-line arguments:
+/// `{"beverage": "regularcoffee", "coffee": 180, "taste": "strong", "profile": 1, "allow_defaults": false, "force": false}`.
+async fn brew_beverage(
+    Extension(state): Extension<SharedState>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, WebError> {
+    let (beverage, ingredients, mode) = parse_beverage_request(&body)?;
+
+    let lock = state.operation.lock().await;
+    let beverage = resolve_beverage(state.ecam.clone(), beverage).await?;
+    let profile = parse_profile(&state.ecam, &body).await?;
+    let recipe =
+        validate_brew_for_profile(state.ecam.clone(), profile, beverage, ingredients, mode).await?;
+    drop(lock);
+
+    // Brewing takes a while, so report progress on the server's display rather than holding the request open
+    let ecam = state.ecam.clone();
+    tokio::spawn(async move {
+        if let Err(e) = brew(ecam, false, beverage, recipe).await {
+            longshot::info!("Brew of {:?} failed: {}", beverage, e);
+        }
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({ "beverage": beverage.to_arg_string() })),
+    ))
+}
+
+/// Saves the requested recipe to a profile. The request body is the same as for `/brew`. The machine saves into its
+/// active profile, so pass `"select_profile": true` to make the profile active first (it stays active afterwards).
+async fn save_beverage_recipe(
+    Extension(state): Extension<SharedState>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, WebError> {
+    let (beverage, ingredients, mode) = parse_beverage_request(&body)?;
+
+    let _lock = state.operation.lock().await;
+    let beverage = resolve_beverage(state.ecam.clone(), beverage).await?;
+    let profile = parse_profile(&state.ecam, &body).await?;
+    if body
+        .get("select_profile")
+        .and_then(Value::as_bool)
+        .unwrap_or_default()
+    {
+        select_profile(state.ecam.clone(), profile).await?;
+    }
+    save_recipe(state.ecam.clone(), profile, beverage, ingredients, mode).await?;
+    Ok(Json(
+        json!({ "beverage": beverage.to_arg_string(), "profile": profile }),
+    ))
+}
+
+/// Stops brewing the beverage named in the request body: `{"beverage": "regularcoffee"}`.
+async fn stop_beverage(
+    Extension(state): Extension<SharedState>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, WebError> {
+    let beverage = body
+        .get("beverage")
+        .and_then(Value::as_str)
+        .ok_or_else(|| WebError::bad_request("Missing beverage"))?;
+    let lock = state.operation.lock().await;
+    let beverage = resolve_beverage(state.ecam.clone(), beverage).await?;
+    drop(lock);
+    stop_brew(state.ecam.clone(), beverage).await?;
+    Ok(Json(status_json(state.ecam.current_state().await?)))
+}
+
+async fn turn_on(Extension(state): Extension<SharedState>) -> Result<impl IntoResponse, WebError> {
+    state
+        .ecam
+        .write_request(Request::AppControl(AppControl::TurnOn))
+        .await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(status_json(state.ecam.current_state().await?)),
+    ))
+}
+
+async fn turn_off(Extension(state): Extension<SharedState>) -> Result<impl IntoResponse, WebError> {
+    state
+        .ecam
+        .write_request(Request::AppControl(AppControl::TurnOff))
+        .await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(status_json(state.ecam.current_state().await?)),
+    ))
+}
+
+async fn status_socket(
+    ws: WebSocketUpgrade,
+    Extension(state): Extension<SharedState>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| stream_status(socket, state))
+}
+
+/// Sends each change in [`EcamStatus`] to the socket until either side goes away. If the server was started with
+/// `--reconnect`, a dropped connection is sent as `{"state": "disconnected"}` and the socket stays open.
+async fn stream_status(mut socket: WebSocket, state: SharedState) {
+    // The machine is polled for its status for as long as the stream is held, so the socket can stay open indefinitely
+    let mut events = match state.ecam.status_stream().await {
+        Ok(events) => Box::pin(events),
+        Err(_) => return,
+    };
+
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let message = match event {
+                    Some(EcamStatusEvent::Status(status, _)) => status_json(status),
+                    Some(EcamStatusEvent::Disconnected) => json!({ "state": "disconnected" }),
+                    Some(_) => continue,
+                    None => break,
+                };
+                if socket.send(Message::Text(message.to_string())).await.is_err() {
+                    break;
+                }
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use longshot::ecam::{DriverSelection, EcamBuilder};
+    use tower::ServiceExt;
+
+    async fn simulator() -> Router {
+        let ecam = EcamBuilder::new(DriverSelection::parse("sim[on]").expect("Failed to parse"))
+            .connect()
+            .await
+            .expect("Failed to connect");
+        router(ecam)
+    }
+
+    async fn call(app: Router, request: Request<Body>) -> (StatusCode, Value) {
+        let response = app.oneshot(request).await.expect("Request failed");
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .expect("Failed to read body");
+        (
+            status,
+            serde_json::from_slice(&body).expect("Body is not JSON"),
+        )
+    }
+
+    #[tokio::test]
+    async fn get_status() {
+        let request = Request::get("/status").body(Body::empty()).unwrap();
+        let (status, body) = call(simulator().await, request).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(json!({ "state": "ready" }), body);
+    }
+
+    #[tokio::test]
+    async fn brew_rejects_bad_ingredients() {
+        let request = Request::post("/brew")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({ "beverage": "regularcoffee", "coffee": 5000 }).to_string(),
+            ))
+            .unwrap();
+        let (status, body) = call(simulator().await, request).await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid ingredients"));
+        assert!(!body["problems"].as_array().unwrap().is_empty());
+    }
+}
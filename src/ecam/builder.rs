@@ -0,0 +1,207 @@
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::ecam_wrapper::DriverFactory;
+use crate::ecam::{
+    get_ecam_simulator, get_ecam_subprocess, Ecam, EcamBT, EcamDriver, EcamError, EcamRecord,
+    EcamReplay, EcamTcp, ReconnectPolicy,
+};
+use crate::prelude::*;
+
+/// The driver used to talk to a device, parsed from a device name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DriverSelection {
+    /// A Bluetooth device, given by its UUID.
+    Bluetooth(Uuid),
+    /// The simulator, given by its full name including any options (ie: `sim[on]`).
+    Simulator(String),
+    /// A service UART, given as `serial:<path>` or `serial:<path>@<baud>`.
+    Serial(String),
+    /// A `longshot bridge`, given as `tcp://<host>:<port>`.
+    Tcp(String),
+    /// A recording made with `--record`, given as `replay:<file>`.
+    Replay(PathBuf),
+}
+
+impl DriverSelection {
+    /// Parses a device name: a Bluetooth UUID, `sim`, `serial:<path>`, `tcp://<host>:<port>` or `replay:<file>`.
+    pub fn parse(device_name: &str) -> Result<Self, EcamError> {
+        Ok(if device_name.starts_with("sim") {
+            DriverSelection::Simulator(device_name.to_owned())
+        } else if let Some(port) = device_name.strip_prefix("serial:") {
+            DriverSelection::Serial(port.to_owned())
+        } else if let Some(address) = device_name.strip_prefix("tcp://") {
+            DriverSelection::Tcp(address.to_owned())
+        } else if let Some(recording) = device_name.strip_prefix("replay:") {
+            DriverSelection::Replay(recording.into())
+        } else if let Ok(uuid) = Uuid::parse_str(device_name) {
+            DriverSelection::Bluetooth(uuid)
+        } else {
+            info!("Unknown device '{}'", device_name);
+            return Err(EcamError::NotFound);
+        })
+    }
+
+    /// Creates the driver in this process.
+    pub async fn connect(&self) -> Result<Box<dyn EcamDriver>, EcamError> {
+        Ok(match self {
+            DriverSelection::Bluetooth(uuid) => Box::new(EcamBT::get(*uuid).await?),
+            DriverSelection::Simulator(name) => Box::new(get_ecam_simulator(name).await?),
+            DriverSelection::Serial(port) => serial_driver(port).await?,
+            DriverSelection::Tcp(address) => Box::new(EcamTcp::connect(address).await?),
+            DriverSelection::Replay(recording) => Box::new(EcamReplay::open(recording)?),
+        })
+    }
+}
+
+impl std::fmt::Display for DriverSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DriverSelection::Bluetooth(uuid) => write!(f, "{}", uuid),
+            DriverSelection::Simulator(name) => write!(f, "{}", name),
+            DriverSelection::Serial(port) => write!(f, "serial:{}", port),
+            DriverSelection::Tcp(address) => write!(f, "tcp://{}", address),
+            DriverSelection::Replay(recording) => write!(f, "replay:{}", recording.display()),
+        }
+    }
+}
+
+async fn serial_driver(port: &str) -> Result<Box<dyn EcamDriver>, EcamError> {
+    Ok(Box::new(crate::ecam::EcamSerial::open(port).await?))
+}
+
+/// Builds an [`Ecam`] for a [`DriverSelection`]. Drivers are created in this process unless
+/// [`EcamBuilder::subprocess`] is set, in which case they run in a child process (the current executable, re-run with
+/// the `x-internal-pipe` subcommand that only the `longshot` binary provides).
+#[derive(Clone, Debug)]
+pub struct EcamBuilder {
+    selection: DriverSelection,
+    subprocess: bool,
+    dump_packets: bool,
+    sync_time: bool,
+    record: Option<PathBuf>,
+    timeout: Option<Duration>,
+    reconnect: Option<ReconnectPolicy>,
+}
+
+impl EcamBuilder {
+    pub fn new(selection: DriverSelection) -> Self {
+        EcamBuilder {
+            selection,
+            subprocess: false,
+            dump_packets: false,
+            sync_time: false,
+            record: None,
+            timeout: None,
+            reconnect: None,
+        }
+    }
+
+    /// Runs the driver in a child process, isolating this process from driver crashes and hangs.
+    pub fn subprocess(mut self, subprocess: bool) -> Self {
+        self.subprocess = subprocess;
+        self
+    }
+
+    /// Dumps decoded packets for debugging.
+    pub fn dump_packets(mut self, dump_packets: bool) -> Self {
+        self.dump_packets = dump_packets;
+        self
+    }
+
+    /// Sets the machine's clock to the host's local time as soon as the driver is ready.
+    pub fn sync_time(mut self, sync_time: bool) -> Self {
+        self.sync_time = sync_time;
+        self
+    }
+
+    /// Records the session to the given file (see [`EcamRecord`]).
+    pub fn record(mut self, record: impl Into<PathBuf>) -> Self {
+        self.record = Some(record.into());
+        self
+    }
+
+    /// Limits how long any wait for the machine may take, replacing the defaults of individual operations (see
+    /// [`Ecam::timeout_or`]).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Reconnects with a fresh driver whenever the device disconnects, following the given policy. Only the first
+    /// connection is recorded (see [`EcamBuilder::record`]).
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Creates the driver, without wrapping it in an [`Ecam`].
+    pub async fn driver(&self) -> Result<Box<dyn EcamDriver>, EcamError> {
+        let mut driver = if self.subprocess {
+            Box::new(get_ecam_subprocess(&self.selection.to_string()).await?)
+        } else {
+            self.selection.connect().await?
+        };
+        if let Some(record) = &self.record {
+            driver = Box::new(EcamRecord::new(driver, record)?);
+        }
+        Ok(driver)
+    }
+
+    /// Connects to the device.
+    pub async fn connect(&self) -> Result<Ecam, EcamError> {
+        let driver = self.driver().await?;
+        let reconnect = self.reconnect.map(|policy| {
+            let builder = EcamBuilder {
+                record: None,
+                ..self.clone()
+            };
+            let factory: DriverFactory = Arc::new(move || {
+                let builder = builder.clone();
+                Box::pin(async move { builder.driver().await })
+            });
+            (policy, factory)
+        });
+        Ok(Ecam::new_reconnecting(
+            driver,
+            self.dump_packets,
+            self.sync_time,
+            self.timeout,
+            reconnect,
+        )
+        .await)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ecam::EcamStatus;
+    use rstest::*;
+
+    #[rstest]
+    #[case("00000000-0000-0000-0000-000000000000")]
+    #[case("sim")]
+    #[case("sim[on,drop=5]")]
+    #[case("serial:/dev/ttyUSB0@9600")]
+    #[case("tcp://localhost:8888")]
+    #[case("replay:session.txt")]
+    fn driver_selection_round_trip(#[case] device_name: &str) {
+        let selection = DriverSelection::parse(device_name).expect("Failed to parse");
+        assert_eq!(device_name, selection.to_string());
+    }
+
+    #[test]
+    fn driver_selection_unknown() {
+        assert!(DriverSelection::parse("kitchen").is_err());
+    }
+
+    #[tokio::test]
+    async fn builder_connects_in_process() -> Result<(), EcamError> {
+        let ecam = EcamBuilder::new(DriverSelection::parse("sim[on]")?)
+            .connect()
+            .await?;
+        assert_eq!(EcamStatus::Ready, ecam.current_state().await?);
+        Ok(())
+    }
+}
@@ -136,7 +136,7 @@ struct EcamPeripheral {
 
 impl EcamPeripheral {
     pub async fn write(&self, data: Vec<u8>) -> Result<(), EcamError> {
-        trace_packet!("{{host->device}} {}", hexdump(&data));
+        trace_packet!("{{host->device}} {}", trace_hexdump(&data));
         Result::Ok(
             self.peripheral
                 .write(
@@ -199,7 +199,7 @@ impl EcamPeripheral {
     pub async fn validate(peripheral: Peripheral) -> Result<Option<Self>, EcamError> {
         let properties = peripheral.properties().await?;
         let is_connected = peripheral.is_connected().await?;
-        let properties = properties.map_or(Err(EcamError::Unknown), Ok)?;
+        let properties = properties.ok_or(EcamError::NotFound)?;
         if let Some(local_name) = properties.local_name {
             if !is_connected {
                 peripheral.connect().await?
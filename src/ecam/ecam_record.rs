@@ -0,0 +1,97 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::ecam::{EcamDriver, EcamDriverOutput, EcamError};
+use crate::prelude::*;
+use crate::protocol::{EcamDriverPacket, EcamRequestId};
+
+use super::stdin_stream::to_line;
+
+/// Wraps another driver, recording every output it produces and every packet the host writes to it, along with the
+/// number of milliseconds since the recording started.
+///
+/// The recording uses the same line format as `x-internal-pipe`, with each line prefixed by its timestamp:
+///
+/// ```text
+/// 0 R: READY
+/// 12 S: 750f0000
+/// 85 R: 750f000000000000000000000000000000
+/// 1520 Q:
+/// ```
+///
+/// `R:` lines are outputs from the device, `S:` lines are packets sent by the host, and `Q:` marks the end of the
+/// session. PIN packets are recorded with their payload zeroed. See [`super::EcamReplay`] to play a recording back.
+pub struct EcamRecord {
+    driver: Box<dyn EcamDriver>,
+    file: std::sync::Mutex<std::fs::File>,
+    started: Instant,
+}
+
+impl EcamRecord {
+    /// Wraps `driver`, recording the session to the file at `path` (which is overwritten if it exists).
+    pub fn new(driver: Box<dyn EcamDriver>, path: &Path) -> Result<Self, EcamError> {
+        Ok(EcamRecord {
+            driver,
+            file: std::sync::Mutex::new(std::fs::File::create(path)?),
+            started: Instant::now(),
+        })
+    }
+
+    fn record(&self, line: String) -> Result<(), EcamError> {
+        let elapsed = self.started.elapsed().as_millis();
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| std::io::Error::other("recording lock poisoned"))?;
+        writeln!(file, "{} {}", elapsed, line)?;
+        Ok(())
+    }
+}
+
+/// Zeroes the payload of sensitive packets so that they never end up in a recording.
+fn redact(packet: EcamDriverPacket) -> EcamDriverPacket {
+    match packet.bytes.first().map(|id| EcamRequestId::try_from(*id)) {
+        Some(Ok(id)) if id.is_sensitive() => {
+            let mut bytes = packet.bytes;
+            bytes.iter_mut().skip(2).for_each(|b| *b = 0);
+            EcamDriverPacket::from_vec(bytes)
+        }
+        _ => packet,
+    }
+}
+
+impl EcamDriver for EcamRecord {
+    fn read(&self) -> AsyncFuture<'_, Option<EcamDriverOutput>> {
+        Box::pin(async {
+            let output = self.driver.read().await?;
+            let line = match output.clone() {
+                Some(EcamDriverOutput::Packet(packet)) => {
+                    to_line(EcamDriverOutput::Packet(redact(packet)))
+                }
+                Some(output) => to_line(output),
+                None => to_line(EcamDriverOutput::Done),
+            };
+            self.record(line)?;
+            Ok(output)
+        })
+    }
+
+    fn write(&self, data: EcamDriverPacket) -> AsyncFuture<'_, ()> {
+        Box::pin(async move {
+            self.record(format!("S: {}", redact(data.clone()).stringify()))?;
+            self.driver.write(data).await
+        })
+    }
+
+    fn alive(&self) -> AsyncFuture<'_, bool> {
+        self.driver.alive()
+    }
+
+    fn scan<'a>() -> AsyncFuture<'a, (String, uuid::Uuid)>
+    where
+        Self: Sized,
+    {
+        Box::pin(async { Err(EcamError::NotFound) })
+    }
+}
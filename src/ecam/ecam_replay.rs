@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use super::stdin_stream::{parse_line, parse_output_line};
+use crate::ecam::{EcamDriver, EcamDriverOutput, EcamError};
+use crate::prelude::*;
+use crate::protocol::{trace_hexdump, EcamDriverPacket};
+
+/// One line of a recording made by [`super::EcamRecord`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum RecordedLine {
+    /// Output from the device.
+    Output(EcamDriverOutput),
+    /// A packet written by the host.
+    Write(EcamDriverPacket),
+}
+
+fn parse_recorded_line(line: &str) -> Option<(Duration, RecordedLine)> {
+    let (elapsed, line) = line.split_once(' ')?;
+    let elapsed = Duration::from_millis(elapsed.parse().ok()?);
+    let line = if let Some(output) = parse_output_line(line) {
+        RecordedLine::Output(output)
+    } else if let Some(EcamDriverOutput::Packet(packet)) = parse_line(line) {
+        RecordedLine::Write(packet)
+    } else {
+        return None;
+    };
+    Some((elapsed, line))
+}
+
+/// How long an output waits for the host writes that preceded it in the recording before it is replayed anyway.
+const REPLAY_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// An output from a recording, along with what happened before it.
+struct ReplayOutput {
+    /// When the output was recorded.
+    elapsed: Duration,
+    /// The number of host writes recorded before this output.
+    writes_before: usize,
+    output: EcamDriverOutput,
+}
+
+/// A driver that plays back a recording made with `--record`. Each output is replayed once the host has sent as many
+/// packets as it had when the output was recorded, keeping responses in step with requests, and outputs are spaced at
+/// least as far apart as they were in the recording. Packets written by the host are compared against the recorded
+/// ones, and any divergence is logged.
+pub struct EcamReplay {
+    outputs: Mutex<VecDeque<ReplayOutput>>,
+    writes: Mutex<VecDeque<EcamDriverPacket>>,
+    written: std::sync::atomic::AtomicUsize,
+    write_notify: tokio::sync::Notify,
+    /// The recorded and actual times of the last replayed output.
+    last: Mutex<(Duration, Instant)>,
+    done: std::sync::atomic::AtomicBool,
+}
+
+impl EcamReplay {
+    /// Loads the recording at `path`.
+    pub fn open(path: &Path) -> Result<Self, EcamError> {
+        Self::from_recording(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses a recording, skipping blank lines and lines starting with `#`.
+    pub fn from_recording(recording: &str) -> Result<Self, EcamError> {
+        let mut outputs = VecDeque::new();
+        let mut writes = VecDeque::new();
+        for (i, line) in recording.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_recorded_line(line) {
+                Some((elapsed, RecordedLine::Output(output))) => outputs.push_back(ReplayOutput {
+                    elapsed,
+                    writes_before: writes.len(),
+                    output,
+                }),
+                Some((_, RecordedLine::Write(packet))) => writes.push_back(packet),
+                None => {
+                    info!("Invalid recording at line {}: {}", i + 1, line);
+                    return Err(EcamError::InvalidArgument(format!(
+                        "invalid recording at line {}",
+                        i + 1
+                    )));
+                }
+            }
+        }
+        Ok(EcamReplay {
+            outputs: Mutex::new(outputs),
+            writes: Mutex::new(writes),
+            written: Default::default(),
+            write_notify: Default::default(),
+            last: Mutex::new((Duration::ZERO, Instant::now())),
+            done: Default::default(),
+        })
+    }
+
+    /// Waits for the host to have written `writes` packets, giving up after [`REPLAY_WRITE_TIMEOUT`].
+    async fn wait_for_writes(&self, writes: usize) {
+        let deadline = Instant::now() + REPLAY_WRITE_TIMEOUT;
+        while self.written.load(std::sync::atomic::Ordering::SeqCst) < writes {
+            if tokio::time::timeout_at(deadline, self.write_notify.notified())
+                .await
+                .is_err()
+            {
+                warning!(
+                    "Replay diverged: the host sent {} packets, but the recording expected {}",
+                    self.written.load(std::sync::atomic::Ordering::SeqCst),
+                    writes
+                );
+                break;
+            }
+        }
+    }
+}
+
+impl EcamDriver for EcamReplay {
+    fn read(&self) -> AsyncFuture<'_, Option<EcamDriverOutput>> {
+        Box::pin(async {
+            // Only take the output once it is due, so that a cancelled read doesn't lose it
+            let (elapsed, writes_before) = match self.outputs.lock().await.front() {
+                Some(next) => (next.elapsed, next.writes_before),
+                None => (Duration::ZERO, 0),
+            };
+            self.wait_for_writes(writes_before).await;
+            let mut last = self.last.lock().await;
+            tokio::time::sleep_until(last.1 + elapsed.saturating_sub(last.0)).await;
+            *last = (elapsed, Instant::now());
+            // Recordings that were cut short end as if the device had disconnected
+            let output = self
+                .outputs
+                .lock()
+                .await
+                .pop_front()
+                .map_or(EcamDriverOutput::Done, |next| next.output);
+            if output == EcamDriverOutput::Done {
+                self.done.store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            Ok(Some(output))
+        })
+    }
+
+    fn write(&self, data: EcamDriverPacket) -> AsyncFuture<'_, ()> {
+        Box::pin(async move {
+            match self.writes.lock().await.pop_front() {
+                Some(expected) if expected == data => {}
+                Some(expected) => warning!(
+                    "Replay diverged: expected {} but host sent {}",
+                    trace_hexdump(&expected.bytes),
+                    trace_hexdump(&data.bytes)
+                ),
+                None => warning!(
+                    "Replay diverged: unexpected packet {}",
+                    trace_hexdump(&data.bytes)
+                ),
+            }
+            self.written
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.write_notify.notify_one();
+            Ok(())
+        })
+    }
+
+    fn alive(&self) -> AsyncFuture<'_, bool> {
+        Box::pin(async { Ok(!self.done.load(std::sync::atomic::Ordering::Relaxed)) })
+    }
+
+    fn scan<'a>() -> AsyncFuture<'a, (String, uuid::Uuid)>
+    where
+        Self: Sized,
+    {
+        Box::pin(async { Err(EcamError::NotFound) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ecam::EcamRecord;
+
+    #[tokio::test]
+    async fn replay_recording() -> Result<(), EcamError> {
+        let replay =
+            EcamReplay::from_recording("# comment\n0 R: READY\n1 S: 750f\n2 R: 750f00\n\n3 Q:\n")?;
+        assert_eq!(replay.read().await?, Some(EcamDriverOutput::Ready));
+        replay
+            .write(EcamDriverPacket::from_slice(&[0x75, 0x0f]))
+            .await?;
+        assert_eq!(
+            replay.read().await?,
+            Some(EcamDriverOutput::Packet(EcamDriverPacket::from_slice(&[
+                0x75, 0x0f, 0x00
+            ])))
+        );
+        assert!(replay.alive().await?);
+        assert_eq!(replay.read().await?, Some(EcamDriverOutput::Done));
+        assert!(!replay.alive().await?);
+        Ok(())
+    }
+
+    #[test]
+    fn replay_rejects_invalid_lines() {
+        assert!(EcamReplay::from_recording("0 R: READY\n1 X: 1234\n").is_err());
+        assert!(EcamReplay::from_recording("R: READY\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn record_then_replay() -> Result<(), EcamError> {
+        let path = std::env::temp_dir().join(format!("longshot-record-{}.txt", std::process::id()));
+        let recorded = EcamReplay::from_recording(
+            "0 R: READY\n0 S: 750f\n0 R: 750f00\n0 S: b1f01234\n0 Q:\n",
+        )?;
+        let record = EcamRecord::new(Box::new(recorded), &path)?;
+        assert_eq!(record.read().await?, Some(EcamDriverOutput::Ready));
+        record
+            .write(EcamDriverPacket::from_slice(&[0x75, 0x0f]))
+            .await?;
+        record.read().await?;
+        // PIN packets have their payload zeroed
+        record
+            .write(EcamDriverPacket::from_slice(&[0xb1, 0xf0, 0x12, 0x34]))
+            .await?;
+        record.read().await?;
+
+        let recording = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        let lines: Vec<_> = recording
+            .lines()
+            .map(|line| line.split_once(' ').expect("Missing timestamp").1)
+            .collect();
+        assert_eq!(
+            lines,
+            vec!["R: READY", "S: 750f", "R: 750f00", "S: b1f00000", "Q:"]
+        );
+
+        let replay = EcamReplay::from_recording(&recording)?;
+        assert_eq!(replay.read().await?, Some(EcamDriverOutput::Ready));
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::sync::Mutex;
+use tokio_serial::{DataBits, FlowControl, Parity, SerialPortBuilderExt, SerialStream, StopBits};
+
+use crate::ecam::{EcamDriver, EcamDriverOutput, EcamError, EcamPacketReceiver};
+use crate::{prelude::*, protocol::*};
+
+use super::packet_stream::packet_stream;
+
+/// The baud rate used when the device name doesn't specify one.
+const DEFAULT_BAUD_RATE: u32 = 115200;
+
+/// Serial implementation of [`EcamDriver`], for machines with their service UART wired to the host (ie: through a
+/// USB-serial adapter). Packets are framed the same way as they are over Bluetooth.
+pub struct EcamSerial {
+    writer: Mutex<WriteHalf<SerialStream>>,
+    notifications: EcamPacketReceiver,
+    alive: Arc<AtomicBool>,
+}
+
+impl EcamSerial {
+    /// Opens the serial port given as `path` or `path@baud` (ie: `/dev/ttyUSB0@9600`) as 8N1 with no flow control.
+    pub async fn open(device: &str) -> Result<Self, EcamError> {
+        let (path, baud) = match device.rsplit_once('@') {
+            Some((path, baud)) => (
+                path,
+                baud.parse().map_err(|_| {
+                    EcamError::InvalidArgument(format!("invalid baud rate '{}'", baud))
+                })?,
+            ),
+            None => (device, DEFAULT_BAUD_RATE),
+        };
+        trace_packet!("Opening serial port {} at {} baud", path, baud);
+        let port = tokio_serial::new(path, baud)
+            .data_bits(DataBits::Eight)
+            .parity(Parity::None)
+            .stop_bits(StopBits::One)
+            .flow_control(FlowControl::None)
+            .open_native_async()
+            .map_err(std::io::Error::from)?;
+        Ok(Self::from_stream(port))
+    }
+
+    fn from_stream(port: SerialStream) -> Self {
+        let (reader, writer) = tokio::io::split(port);
+        let alive = Arc::new(AtomicBool::new(true));
+        let bytes = Box::pin(read_stream(reader, alive.clone()));
+        let notifications = EcamPacketReceiver::from_stream(
+            Box::pin(packet_stream(bytes).map(|v| {
+                EcamDriverOutput::Packet(EcamDriverPacket::from_slice(unwrap_packet(&v)))
+            })),
+            true,
+        );
+        EcamSerial {
+            writer: Mutex::new(writer),
+            notifications,
+            alive,
+        }
+    }
+
+    async fn write(&self, data: Vec<u8>) -> Result<(), EcamError> {
+        trace_packet!("{{host->device}} {}", trace_hexdump(&data));
+        Ok(self.writer.lock().await.write_all(&data).await?)
+    }
+}
+
+/// Reads raw chunks of bytes from the port until it is closed or fails, then marks the port as no longer alive.
+fn read_stream(
+    mut reader: ReadHalf<SerialStream>,
+    alive: Arc<AtomicBool>,
+) -> impl Stream<Item = Vec<u8>> {
+    async_stream::stream! {
+        let mut buffer = [0; 256];
+        loop {
+            match reader.read(&mut buffer).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => yield buffer[..n].to_vec(),
+            }
+        }
+        trace_shutdown!("EcamSerial::read_stream()");
+        alive.store(false, Ordering::Relaxed);
+    }
+}
+
+impl EcamDriver for EcamSerial {
+    fn read(&self) -> AsyncFuture<'_, Option<EcamDriverOutput>> {
+        Box::pin(self.notifications.recv())
+    }
+
+    fn write(&self, data: EcamDriverPacket) -> AsyncFuture<'_, ()> {
+        Box::pin(async move { self.write(data.packetize()).await })
+    }
+
+    fn alive(&self) -> AsyncFuture<'_, bool> {
+        Box::pin(async { Ok(self.alive.load(Ordering::Relaxed)) })
+    }
+
+    fn scan<'a>() -> AsyncFuture<'a, (String, uuid::Uuid)>
+    where
+        Self: Sized,
+    {
+        Box::pin(async { Err(EcamError::NotFound) })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn serial_round_trip() -> Result<(), EcamError> {
+        let (mut controller, device) = SerialStream::pair().map_err(std::io::Error::from)?;
+        let serial = EcamSerial::from_stream(device);
+        assert_eq!(Some(EcamDriverOutput::Ready), serial.read().await?);
+
+        // A packet from the machine, split across writes and preceded by line noise
+        let response = crate::protocol::test::RESPONSE_STATUS_STANDBY_NO_ALARMS;
+        controller.write_all(&[0x01, 0x02]).await?;
+        controller.write_all(&response[..3]).await?;
+        controller.flush().await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        controller.write_all(&response[3..]).await?;
+        assert_eq!(
+            Some(EcamDriverOutput::Packet(EcamDriverPacket::from_slice(
+                unwrap_packet(&response)
+            ))),
+            serial.read().await?
+        );
+
+        // A packet from the host is framed before it is sent
+        let packet = EcamDriverPacket::from_vec(Request::MonitorV2().encode());
+        let framed = packet.packetize();
+        EcamDriver::write(&serial, packet).await?;
+        let mut received = vec![0; framed.len()];
+        controller.read_exact(&mut received).await?;
+        assert_eq!(framed, received);
+        Ok(())
+    }
+}
@@ -1,15 +1,334 @@
+use std::time::Instant;
 use tokio::sync::Mutex;
 
 use crate::ecam::{EcamDriver, EcamDriverOutput, EcamError};
 use crate::prelude::*;
 use crate::protocol::{
-    hexdump, EcamAccessory, EcamBeverageId, EcamDriverPacket, EcamMachineState, EcamMachineSwitch,
-    EcamRequestId, MonitorV2Response, PartialEncode, SwitchSet,
+    trace_hexdump, AppControl, EcamAccessory, EcamBeverageId, EcamBeverageTasteType,
+    EcamDriverPacket, EcamIngredients, EcamMachineAlarm, EcamMachineState, EcamMachineSwitch,
+    EcamOperationTrigger, EcamRequestId, MachineEnumerable, MonitorV2Response, PartialDecode,
+    PartialEncode, RecipeInfo, SwitchSet,
 };
 
+/// How long the simulated machine takes to turn on or off.
+const POWER_DURATION: Duration = Duration::from_millis(2500);
+/// How long the simulated machine takes to dispense one unit of coffee, milk or hot water.
+const DISPENSE_DURATION_PER_UNIT: Duration = Duration::from_millis(20);
+/// The shortest time the simulated machine will spend dispensing a beverage.
+const MIN_DISPENSE_DURATION: Duration = Duration::from_secs(1);
+
 struct EcamSimulate {
     rx: Mutex<tokio::sync::mpsc::Receiver<EcamDriverOutput>>,
     tx: Mutex<tokio::sync::mpsc::Sender<EcamDriverOutput>>,
+    state: Mutex<SimulatorState>,
+}
+
+/// What the simulated machine is currently doing.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SimulatorActivity {
+    StandBy,
+    TurningOn(Instant),
+    Ready,
+    Dispensing {
+        beverage: EcamBeverageId,
+        started: Instant,
+        duration: Duration,
+    },
+    ShuttingDown(Instant),
+}
+
+/// Simulator options, given as a comma-separated list in brackets after the simulator's name, ie:
+/// `sim[on,alarm=coffeewastecontainerfull@50,accessory=milk,drop=5]`.
+///
+///  * `on`: start with the machine already on.
+///  * `alarm=<alarm>`: raise the alarm from the start.
+///  * `alarm=<alarm>@<percentage>`: raise the alarm, cancelling the brew, once a brew reaches the given percentage.
+///  * `accessory=<accessory>`: report the given accessory as connected.
+///  * `switch=<switch>`: report the given switch as set, in addition to the water spout.
+///  * `drop=<n>`: drop every n-th packet the simulator would send.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SimulatorConfig {
+    on: bool,
+    alarms: Vec<(EcamMachineAlarm, Option<u8>)>,
+    accessory: EcamAccessory,
+    switches: Vec<EcamMachineSwitch>,
+    drop_every: Option<usize>,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        SimulatorConfig {
+            on: false,
+            alarms: vec![],
+            accessory: EcamAccessory::None,
+            switches: vec![EcamMachineSwitch::WaterSpout],
+            drop_every: None,
+        }
+    }
+}
+
+impl SimulatorConfig {
+    fn parse(simulator: &str) -> Result<Self, String> {
+        let mut config = SimulatorConfig::default();
+        let options = match simulator.split_once('[') {
+            Some((_, options)) => options
+                .strip_suffix(']')
+                .ok_or_else(|| format!("Missing ']' in simulator options '{}'", simulator))?,
+            None => return Ok(config),
+        };
+        for option in options.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+            let (key, value) = option.split_once('=').unwrap_or((option, ""));
+            let lookup_error =
+                || format!("Unknown value '{}' for simulator option '{}'", value, key);
+            match key {
+                "on" => config.on = true,
+                "alarm" => {
+                    let (alarm, at) = match value.split_once('@') {
+                        Some((alarm, at)) => {
+                            (alarm, Some(at.parse::<u8>().map_err(|_| lookup_error())?))
+                        }
+                        None => (value, None),
+                    };
+                    let alarm = EcamMachineAlarm::lookup_by_name_case_insensitive(alarm)
+                        .filter(|alarm| (*alarm as u8) < 16)
+                        .ok_or_else(lookup_error)?;
+                    config.alarms.push((alarm, at));
+                }
+                "accessory" => {
+                    config.accessory = EcamAccessory::lookup_by_name_case_insensitive(value)
+                        .ok_or_else(lookup_error)?
+                }
+                "switch" => config.switches.push(
+                    EcamMachineSwitch::lookup_by_name_case_insensitive(value)
+                        .ok_or_else(lookup_error)?,
+                ),
+                "drop" => {
+                    config.drop_every = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(lookup_error)?,
+                    )
+                }
+                _ => return Err(format!("Unknown simulator option '{}'", key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// A state machine that reacts to power, brew and stop requests the way the machine does. Time-based transitions
+/// (turning on, dispensing, shutting down) are evaluated lazily against the time passed in, which keeps this testable.
+struct SimulatorState {
+    activity: SimulatorActivity,
+    config: SimulatorConfig,
+    /// The alarms that have been raised so far.
+    alarms: Vec<EcamMachineAlarm>,
+    /// The number of packets we have generated, used to decide which ones to drop.
+    sent: usize,
+}
+
+impl SimulatorState {
+    fn new(config: SimulatorConfig) -> Self {
+        SimulatorState {
+            activity: if config.on {
+                SimulatorActivity::Ready
+            } else {
+                SimulatorActivity::StandBy
+            },
+            alarms: config
+                .alarms
+                .iter()
+                .filter(|(_, at)| at.is_none())
+                .map(|(alarm, _)| *alarm)
+                .collect(),
+            config,
+            sent: 0,
+        }
+    }
+
+    /// Applies any time-based transitions that have completed by `now`.
+    fn advance(&mut self, now: Instant) {
+        // Alarms that are raised mid-brew cancel the brew
+        if let SimulatorActivity::Dispensing {
+            started, duration, ..
+        } = self.activity
+        {
+            let percentage = percentage_between(started, duration, now);
+            for (alarm, at) in self.config.alarms.iter() {
+                if matches!(at, Some(at) if percentage >= *at) && !self.alarms.contains(alarm) {
+                    self.alarms.push(*alarm);
+                    self.activity = SimulatorActivity::Ready;
+                }
+            }
+        }
+        self.activity = match self.activity {
+            SimulatorActivity::TurningOn(started) if now - started >= POWER_DURATION => {
+                SimulatorActivity::Ready
+            }
+            SimulatorActivity::Dispensing {
+                started, duration, ..
+            } if now - started >= duration => SimulatorActivity::Ready,
+            SimulatorActivity::ShuttingDown(started) if now - started >= POWER_DURATION => {
+                SimulatorActivity::StandBy
+            }
+            activity => activity,
+        }
+    }
+
+    /// Returns the `(state, progress, percentage)` reported in monitor responses at `now`.
+    fn status(&mut self, now: Instant) -> (EcamMachineState, u8, u8) {
+        self.advance(now);
+        let percentage = |started, duration| percentage_between(started, duration, now);
+        match self.activity {
+            SimulatorActivity::StandBy => (EcamMachineState::StandBy, 0, 0),
+            SimulatorActivity::TurningOn(started) => (
+                EcamMachineState::TurningOn,
+                0,
+                percentage(started, POWER_DURATION),
+            ),
+            SimulatorActivity::Ready => (EcamMachineState::ReadyOrDispensing, 0, 0),
+            SimulatorActivity::Dispensing {
+                started, duration, ..
+            } => {
+                let percentage = percentage(started, duration);
+                (
+                    EcamMachineState::ReadyOrDispensing,
+                    percentage / 4 + 1,
+                    percentage,
+                )
+            }
+            SimulatorActivity::ShuttingDown(started) => (
+                EcamMachineState::ShuttingDown,
+                0,
+                percentage(started, POWER_DURATION),
+            ),
+        }
+    }
+
+    /// Handles a packet from the host at `now`, returning the packets the machine would send in response (minus any we
+    /// have been configured to drop).
+    fn handle(&mut self, packet: &[u8], now: Instant) -> Vec<Vec<u8>> {
+        let mut responses = self.respond(packet, now);
+        if let Some(drop_every) = self.config.drop_every {
+            responses.retain(|_| {
+                self.sent += 1;
+                self.sent % drop_every != 0
+            });
+        }
+        responses
+    }
+
+    fn respond(&mut self, packet: &[u8], now: Instant) -> Vec<Vec<u8>> {
+        self.advance(now);
+        let id = match packet.first().map(|id| EcamRequestId::try_from(*id)) {
+            Some(Ok(id)) => id,
+            _ => return vec![],
+        };
+        match id {
+            EcamRequestId::MonitorV2 => {
+                let (state, progress, percentage) = self.status(now);
+                vec![make_simulated_response(
+                    state,
+                    progress,
+                    percentage,
+                    self.config.accessory,
+                    &self.config.switches,
+                    &self.alarms,
+                )]
+            }
+            EcamRequestId::AppControl => {
+                let request = &packet[2..];
+                if request.starts_with(&AppControl::TurnOn.encode())
+                    && self.activity == SimulatorActivity::StandBy
+                {
+                    self.activity = SimulatorActivity::TurningOn(now);
+                } else if request.starts_with(&AppControl::TurnOff.encode())
+                    && self.activity == SimulatorActivity::Ready
+                {
+                    self.activity = SimulatorActivity::ShuttingDown(now);
+                }
+                vec![]
+            }
+            EcamRequestId::BeverageDispensingMode => {
+                self.handle_dispense(packet, now);
+                vec![vec![packet[0], 0xf0, 0, 0]]
+            }
+            EcamRequestId::RecipeQuantityRead => {
+                let mut response = vec![packet[0], 0xf0, packet[2], packet[3]];
+                if let Ok(beverage) = packet[3].try_into() {
+                    if let Some((recipe, _)) = get_recipes(beverage) {
+                        response = [response, recipe].concat();
+                    }
+                }
+                vec![response]
+            }
+            EcamRequestId::RecipeMinMaxSync => {
+                let mut response = vec![packet[0], 0xf0, packet[2]];
+                if let Ok(beverage) = packet[2].try_into() {
+                    if let Some((_, minmax)) = get_recipes(beverage) {
+                        response = [response, minmax].concat();
+                    }
+                }
+                vec![response]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Starts or stops dispensing in response to a `BeverageDispensingMode` packet, laid out as beverage, trigger,
+    /// ingredients and mode.
+    fn handle_dispense(&mut self, packet: &[u8], now: Instant) {
+        if packet.len() < 5 {
+            return;
+        }
+        let beverage = match EcamBeverageId::try_from(packet[2]) {
+            Ok(beverage) => beverage,
+            Err(_) => return,
+        };
+        let trigger = EcamOperationTrigger::try_from(packet[3]);
+        let mode = EcamBeverageTasteType::try_from(packet[packet.len() - 1]);
+        match (trigger, mode) {
+            (Ok(EcamOperationTrigger::Stop), _) => {
+                if matches!(self.activity, SimulatorActivity::Dispensing { beverage: b, .. } if b == beverage)
+                {
+                    self.activity = SimulatorActivity::Ready;
+                }
+            }
+            (
+                Ok(EcamOperationTrigger::Start),
+                Ok(
+                    EcamBeverageTasteType::Prepare
+                    | EcamBeverageTasteType::PrepareInversion
+                    | EcamBeverageTasteType::PrepareAndSave
+                    | EcamBeverageTasteType::PrepareAndSaveInversion,
+                ),
+            ) => {
+                if self.activity != SimulatorActivity::Ready || !self.alarms.is_empty() {
+                    return;
+                }
+                let ingredients =
+                    <Vec<RecipeInfo<u16>>>::partial_decode(&mut &packet[4..packet.len() - 1])
+                        .unwrap_or_default();
+                let units: u32 = ingredients
+                    .iter()
+                    .filter(|i| {
+                        i.ingredient == EcamIngredients::Coffee
+                            || i.ingredient == EcamIngredients::Milk
+                            || i.ingredient == EcamIngredients::HotWater
+                    })
+                    .map(|i| i.value as u32)
+                    .sum();
+                self.activity = SimulatorActivity::Dispensing {
+                    beverage,
+                    started: now,
+                    duration: (DISPENSE_DURATION_PER_UNIT * units).max(MIN_DISPENSE_DURATION),
+                };
+            }
+            _ => {}
+        }
+    }
 }
 
 /// These are the recipes the simulator will make
@@ -122,25 +441,11 @@ impl EcamDriver for EcamSimulate {
     }
 
     fn write(&self, data: crate::protocol::EcamDriverPacket) -> AsyncFuture<()> {
-        trace_packet!("{{host->device}} {}", hexdump(&data.bytes));
+        trace_packet!("{{host->device}} {}", trace_hexdump(&data.bytes));
         Box::pin(async move {
-            if data.bytes[0] == EcamRequestId::RecipeQuantityRead as u8 {
-                let mut packet = vec![data.bytes[0], 0xf0, 1, data.bytes[3]];
-                if let Ok(beverage) = data.bytes[3].try_into() {
-                    if let Some((recipe, _)) = get_recipes(beverage) {
-                        packet = [packet, recipe].concat();
-                    }
-                }
-                send(&*self.tx.lock().await, packet).await?;
-            }
-            if data.bytes[0] == EcamRequestId::RecipeMinMaxSync as u8 {
-                let mut packet = vec![data.bytes[0], 0xf0, data.bytes[2]];
-                if let Ok(beverage) = data.bytes[2].try_into() {
-                    if let Some((_, minmax)) = get_recipes(beverage) {
-                        packet = [packet, minmax].concat();
-                    }
-                }
-                send(&*self.tx.lock().await, packet).await?;
+            let responses = self.state.lock().await.handle(&data.bytes, Instant::now());
+            for response in responses {
+                send(&*self.tx.lock().await, response).await?;
             }
             Ok(())
         })
@@ -158,15 +463,27 @@ impl EcamDriver for EcamSimulate {
     }
 }
 
+/// The percentage of `duration` that has elapsed between `started` and `now`.
+fn percentage_between(started: Instant, duration: Duration, now: Instant) -> u8 {
+    ((now - started).as_millis() * 100 / duration.as_millis().max(1)).min(100) as u8
+}
+
 /// Create a Vec<u8> that mocks a machine response.
-fn make_simulated_response(state: EcamMachineState, progress: u8, percentage: u8) -> Vec<u8> {
+fn make_simulated_response(
+    state: EcamMachineState,
+    progress: u8,
+    percentage: u8,
+    accessory: EcamAccessory,
+    switches: &[EcamMachineSwitch],
+    alarms: &[EcamMachineAlarm],
+) -> Vec<u8> {
     let mut v = vec![EcamRequestId::MonitorV2.into(), 0xf0];
     v.extend_from_slice(
         &MonitorV2Response {
             state: state.into(),
-            accessory: EcamAccessory::None.into(),
-            switches: SwitchSet::of(&[EcamMachineSwitch::WaterSpout]),
-            alarms: SwitchSet::empty(),
+            accessory: accessory.into(),
+            switches: SwitchSet::of(switches),
+            alarms: SwitchSet::of(alarms),
             progress,
             percentage,
             ..Default::default()
@@ -178,7 +495,7 @@ fn make_simulated_response(state: EcamMachineState, progress: u8, percentage: u8
 
 fn eat_errors_with_warning<T: std::fmt::Debug>(e: T) -> EcamError {
     warning!("{:?}", e);
-    EcamError::Unknown
+    EcamError::Disconnected
 }
 
 async fn send_output(
@@ -192,77 +509,200 @@ async fn send(
     tx: &tokio::sync::mpsc::Sender<EcamDriverOutput>,
     v: Vec<u8>,
 ) -> Result<(), EcamError> {
-    trace_packet!("{}", hexdump(&v));
+    trace_packet!("{}", trace_hexdump(&v));
     send_output(tx, EcamDriverOutput::Packet(EcamDriverPacket::from_vec(v))).await
 }
 
+/// Creates a simulated machine that answers status, recipe, power, brew and stop requests. The simulator starts in
+/// standby unless the `on` option is given (ie: `sim[on]`). See [`SimulatorConfig`] for the other options.
 pub async fn get_ecam_simulator(simulator: &str) -> Result<impl EcamDriver, EcamError> {
+    let config = SimulatorConfig::parse(simulator).map_err(|e| {
+        info!("{}", e);
+        EcamError::InvalidArgument(e)
+    })?;
     let (tx, rx) = tokio::sync::mpsc::channel(1);
-    const DELAY: Duration = Duration::from_millis(250);
     send_output(&tx, EcamDriverOutput::Ready).await?;
-    let tx_out = tx.clone();
-    let on = simulator.ends_with("[on]");
     trace_packet!("Initializing simulator: {}", simulator);
-    tokio::spawn(async move {
-        if !on {
-            // Start in standby
-            for _ in 0..5 {
-                send(
-                    &tx,
-                    make_simulated_response(EcamMachineState::StandBy, 0, 0),
-                )
-                .await?;
-                tokio::time::sleep(DELAY).await;
-            }
+    Ok(EcamSimulate {
+        rx: Mutex::new(rx),
+        tx: Mutex::new(tx),
+        state: Mutex::new(SimulatorState::new(config)),
+    })
+}
 
-            // Turning on
-            for i in 0..5 {
-                send(
-                    &tx,
-                    make_simulated_response(EcamMachineState::TurningOn, 0, i * 20),
-                )
-                .await?;
-                tokio::time::sleep(DELAY).await;
-            }
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        // Ready
-        for _ in 0..3 {
-            send(
-                &tx,
-                make_simulated_response(EcamMachineState::ReadyOrDispensing, 0, 0),
-            )
-            .await?;
-            tokio::time::sleep(DELAY).await;
-        }
+    fn brew_packet(
+        beverage: EcamBeverageId,
+        trigger: EcamOperationTrigger,
+        coffee: u16,
+    ) -> Vec<u8> {
+        crate::protocol::Request::BeverageDispensingMode(
+            beverage.into(),
+            trigger.into(),
+            vec![RecipeInfo::new(EcamIngredients::Coffee, coffee)],
+            EcamBeverageTasteType::Prepare.into(),
+        )
+        .encode()
+    }
 
-        // Dispensing
-        for i in 0..25 {
-            send(
-                &tx,
-                make_simulated_response(EcamMachineState::ReadyOrDispensing, i, i * 4),
-            )
-            .await?;
-            tokio::time::sleep(DELAY).await;
-        }
+    #[test]
+    fn simulator_turns_on_only_when_asked() {
+        let start = Instant::now();
+        let mut sim = SimulatorState::new(SimulatorConfig::parse("sim").unwrap());
+        assert_eq!(
+            sim.status(start + POWER_DURATION * 2).0,
+            EcamMachineState::StandBy
+        );
 
-        // Ready forever
-        for _ in 0..10 {
-            send(
-                &tx,
-                make_simulated_response(EcamMachineState::ReadyOrDispensing, 0, 0),
+        let now = start + POWER_DURATION * 2;
+        sim.handle(
+            &crate::protocol::Request::AppControl(AppControl::TurnOn).encode(),
+            now,
+        );
+        assert_eq!(sim.status(now).0, EcamMachineState::TurningOn);
+        assert_eq!(
+            sim.status(now + POWER_DURATION).0,
+            EcamMachineState::ReadyOrDispensing
+        );
+    }
+
+    #[test]
+    fn simulator_dispenses_in_proportion_to_quantity() {
+        let start = Instant::now();
+        let mut sim = SimulatorState::new(SimulatorConfig::parse("sim[on]").unwrap());
+        sim.handle(
+            &brew_packet(
+                EcamBeverageId::RegularCoffee,
+                EcamOperationTrigger::Start,
+                200,
+            ),
+            start,
+        );
+        let (state, progress, percentage) = sim.status(start + DISPENSE_DURATION_PER_UNIT * 100);
+        assert_eq!(state, EcamMachineState::ReadyOrDispensing);
+        assert_ne!(progress, 0);
+        assert_eq!(percentage, 50);
+        assert_eq!(
+            sim.status(start + DISPENSE_DURATION_PER_UNIT * 200),
+            (EcamMachineState::ReadyOrDispensing, 0, 0)
+        );
+    }
+
+    #[test]
+    fn simulator_honours_stop() {
+        let start = Instant::now();
+        let mut sim = SimulatorState::new(SimulatorConfig::parse("sim[on]").unwrap());
+        sim.handle(
+            &brew_packet(
+                EcamBeverageId::RegularCoffee,
+                EcamOperationTrigger::Start,
+                200,
+            ),
+            start,
+        );
+        let now = start + DISPENSE_DURATION_PER_UNIT * 10;
+        assert_ne!(sim.status(now).1, 0);
+        sim.handle(
+            &brew_packet(EcamBeverageId::RegularCoffee, EcamOperationTrigger::Stop, 0),
+            now,
+        );
+        assert_eq!(sim.status(now), (EcamMachineState::ReadyOrDispensing, 0, 0));
+    }
+
+    #[test]
+    fn simulator_config() {
+        assert_eq!(
+            SimulatorConfig::parse("sim"),
+            Ok(SimulatorConfig::default())
+        );
+        let config =
+            SimulatorConfig::parse("sim[on, alarm=EmptyWaterTank, alarm=coffeewastecontainerfull@50, accessory=milk, switch=waterlevellow, drop=3]")
+                .unwrap();
+        assert!(config.on);
+        assert_eq!(
+            config.alarms,
+            vec![
+                (EcamMachineAlarm::EmptyWaterTank, None),
+                (EcamMachineAlarm::CoffeeWasteContainerFull, Some(50))
+            ]
+        );
+        assert_eq!(config.accessory, EcamAccessory::Milk);
+        assert_eq!(
+            config.switches,
+            vec![
+                EcamMachineSwitch::WaterSpout,
+                EcamMachineSwitch::WaterLevelLow
+            ]
+        );
+        assert_eq!(config.drop_every, Some(3));
+        assert!(SimulatorConfig::parse("sim[alarm=nope]").is_err());
+        assert!(SimulatorConfig::parse("sim[drop=0]").is_err());
+        assert!(SimulatorConfig::parse("sim[bogus]").is_err());
+    }
+
+    #[test]
+    fn simulator_alarm_cancels_brew() {
+        let start = Instant::now();
+        let mut sim = SimulatorState::new(
+            SimulatorConfig::parse("sim[on,alarm=coffeewastecontainerfull@50]").unwrap(),
+        );
+        let monitor = crate::protocol::Request::MonitorV2().encode();
+        let decode = |packet: &[u8]| {
+            <crate::protocol::Response>::partial_decode(&mut &packet[..]).expect("Failed to decode")
+        };
+        assert_eq!(
+            decode(&sim.handle(&monitor, start)[0]),
+            crate::protocol::Response::MonitorV2(
+                MonitorV2Response::partial_decode(
+                    &mut &make_simulated_response(
+                        EcamMachineState::ReadyOrDispensing,
+                        0,
+                        0,
+                        EcamAccessory::None,
+                        &[EcamMachineSwitch::WaterSpout],
+                        &[]
+                    )[2..]
+                )
+                .unwrap()
             )
-            .await?;
-            tokio::time::sleep(DELAY).await;
-        }
+        );
+        sim.handle(
+            &brew_packet(
+                EcamBeverageId::RegularCoffee,
+                EcamOperationTrigger::Start,
+                200,
+            ),
+            start,
+        );
+        assert_ne!(sim.status(start + DISPENSE_DURATION_PER_UNIT * 50).1, 0);
+        assert_eq!(
+            sim.status(start + DISPENSE_DURATION_PER_UNIT * 100),
+            (EcamMachineState::ReadyOrDispensing, 0, 0)
+        );
+        assert_eq!(sim.alarms, vec![EcamMachineAlarm::CoffeeWasteContainerFull]);
 
-        send_output(&tx, EcamDriverOutput::Done).await?;
+        // Brewing is refused while the alarm is raised
+        let now = start + DISPENSE_DURATION_PER_UNIT * 100;
+        sim.handle(
+            &brew_packet(
+                EcamBeverageId::RegularCoffee,
+                EcamOperationTrigger::Start,
+                200,
+            ),
+            now,
+        );
+        assert_eq!(sim.status(now).1, 0);
+    }
 
-        trace_shutdown!("EcamSimulate");
-        Result::<(), EcamError>::Ok(())
-    });
-    Ok(EcamSimulate {
-        rx: Mutex::new(rx),
-        tx: Mutex::new(tx_out),
-    })
+    #[test]
+    fn simulator_drops_packets() {
+        let now = Instant::now();
+        let mut sim = SimulatorState::new(SimulatorConfig::parse("sim[drop=2]").unwrap());
+        let monitor = crate::protocol::Request::MonitorV2().encode();
+        let received: Vec<usize> = (0..4).map(|_| sim.handle(&monitor, now).len()).collect();
+        assert_eq!(received, vec![1, 0, 1, 0]);
+    }
 }
@@ -0,0 +1,203 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{tcp::OwnedWriteHalf, TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio_stream::wrappers::LinesStream;
+
+use crate::ecam::{EcamBuilder, EcamDriver, EcamDriverOutput, EcamError, EcamPacketReceiver};
+use crate::{prelude::*, protocol::*};
+
+use super::stdin_stream::{parse_line, parse_output_line, to_line};
+
+/// TCP implementation of [`EcamDriver`], talking to a `longshot bridge` running next to the machine. The bridge
+/// carries the same line protocol as `x-internal-pipe`.
+pub struct EcamTcp {
+    writer: Mutex<OwnedWriteHalf>,
+    notifications: EcamPacketReceiver,
+    alive: Arc<AtomicBool>,
+}
+
+impl EcamTcp {
+    /// Connects to the bridge at `address` (ie: `raspberrypi.local:8888`).
+    pub async fn connect(address: &str) -> Result<Self, EcamError> {
+        trace_packet!("Connecting to bridge at {}", address);
+        let (reader, writer) = TcpStream::connect(address).await?.into_split();
+        let alive = Arc::new(AtomicBool::new(true));
+        let mut lines = LinesStream::new(BufReader::new(reader).lines());
+        let alive_stream = alive.clone();
+        let notifications = EcamPacketReceiver::from_stream(
+            Box::pin(async_stream::stream! {
+                while let Some(Ok(line)) = lines.next().await {
+                    if let Some(output) = parse_output_line(&line) {
+                        yield output;
+                    } else if let Some(error) = line.strip_prefix("E: ") {
+                        info!("Bridge error: {}", error);
+                    } else {
+                        trace_packet!("{{bridge}} {}", line);
+                    }
+                }
+                trace_shutdown!("EcamTcp");
+                alive_stream.store(false, Ordering::Relaxed);
+            }),
+            false,
+        );
+        Ok(EcamTcp {
+            writer: Mutex::new(writer),
+            notifications,
+            alive,
+        })
+    }
+}
+
+impl EcamDriver for EcamTcp {
+    fn read(&self) -> AsyncFuture<'_, Option<EcamDriverOutput>> {
+        Box::pin(self.notifications.recv())
+    }
+
+    fn write(&self, data: EcamDriverPacket) -> AsyncFuture<'_, ()> {
+        Box::pin(async move {
+            trace_packet!("{{host->bridge}} {}", trace_hexdump(&data.bytes));
+            let line = format!("S: {}\n", data.stringify());
+            Ok(self.writer.lock().await.write_all(line.as_bytes()).await?)
+        })
+    }
+
+    fn alive(&self) -> AsyncFuture<'_, bool> {
+        Box::pin(async { Ok(self.alive.load(Ordering::Relaxed)) })
+    }
+
+    fn scan<'a>() -> AsyncFuture<'a, (String, uuid::Uuid)>
+    where
+        Self: Sized,
+    {
+        Box::pin(async { Err(EcamError::NotFound) })
+    }
+}
+
+/// Listens for [`EcamTcp`] clients on `listen`, creating a driver with `builder` on behalf of each one in turn and
+/// forwarding packets in both directions until either side disconnects. Only one client may use the device at a time,
+/// so other clients are refused with an error line while one is connected.
+///
+/// The bridge doesn't authenticate or encrypt connections, so anyone who can reach `listen` can control the machine.
+pub async fn bridge(listen: SocketAddr, builder: EcamBuilder) -> Result<(), EcamError> {
+    let listener = TcpListener::bind(listen).await?;
+    info!("Bridging on {}", listen);
+    bridge_listener(listener, builder).await
+}
+
+async fn bridge_listener(listener: TcpListener, builder: EcamBuilder) -> Result<(), EcamError> {
+    let in_use = Arc::new(tokio::sync::Semaphore::new(1));
+    loop {
+        let (mut socket, peer) = listener.accept().await?;
+        let permit = match in_use.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                info!("Refusing client {} as another client is connected", peer);
+                let _ = socket
+                    .write_all(b"E: the bridge is in use by another client\n")
+                    .await;
+                continue;
+            }
+        };
+        info!("Client {} connected", peer);
+        let builder = builder.clone();
+        tokio::spawn(async move {
+            match builder.driver().await {
+                Ok(driver) => {
+                    if let Err(e) = bridge_connection(socket, driver).await {
+                        warning!("Bridge connection failed: {:?}", e);
+                    }
+                }
+                Err(e) => warning!("Failed to connect to the device: {:?}", e),
+            }
+            info!("Client {} disconnected", peer);
+            drop(permit);
+        });
+    }
+}
+
+async fn bridge_connection(
+    socket: TcpStream,
+    driver: Box<dyn EcamDriver>,
+) -> Result<(), EcamError> {
+    let (reader, mut writer) = socket.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        tokio::select! {
+            output = driver.read() => {
+                let output = output?.unwrap_or(EcamDriverOutput::Done);
+                let done = output == EcamDriverOutput::Done;
+                writer.write_all(format!("{}\n", to_line(output)).as_bytes()).await?;
+                if done {
+                    break;
+                }
+            }
+            line = lines.next_line() => {
+                match line?.as_deref().map(parse_line) {
+                    Some(Some(EcamDriverOutput::Packet(packet))) => driver.write(packet).await?,
+                    Some(Some(EcamDriverOutput::Done)) | None => break,
+                    Some(_) => warning!("Invalid line from bridge client"),
+                }
+            }
+        }
+    }
+    trace_shutdown!("bridge_connection()");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ecam::{DriverSelection, EcamReplay};
+
+    #[tokio::test]
+    async fn bridge_round_trip() -> Result<(), EcamError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await?;
+            let device = EcamReplay::from_recording("0 R: READY\n0 S: 750f\n0 R: 750f00\n")?;
+            bridge_connection(socket, Box::new(device)).await
+        });
+
+        let client = EcamTcp::connect(&address.to_string()).await?;
+        assert_eq!(Some(EcamDriverOutput::Ready), client.read().await?);
+        client
+            .write(EcamDriverPacket::from_slice(&[0x75, 0x0f]))
+            .await?;
+        assert_eq!(
+            Some(EcamDriverOutput::Packet(EcamDriverPacket::from_slice(&[
+                0x75, 0x0f, 0x00
+            ]))),
+            client.read().await?
+        );
+        // The end of the recording is forwarded to the client as the device disconnecting
+        assert_eq!(Some(EcamDriverOutput::Done), client.read().await?);
+        server.await.expect("Failed to join")?;
+        assert_eq!(None, client.read().await?);
+        assert!(!client.alive().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bridge_refuses_second_client() -> Result<(), EcamError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let builder = EcamBuilder::new(DriverSelection::parse("sim[on]")?);
+        let server = tokio::spawn(bridge_listener(listener, builder));
+
+        let first = EcamTcp::connect(&address.to_string()).await?;
+        assert_eq!(Some(EcamDriverOutput::Ready), first.read().await?);
+
+        let mut second = BufReader::new(TcpStream::connect(address).await?).lines();
+        assert_eq!(
+            Some("E: the bridge is in use by another client".to_owned()),
+            second.next_line().await?
+        );
+        assert_eq!(None, second.next_line().await?);
+        assert!(first.alive().await?);
+        server.abort();
+        Ok(())
+    }
+}
@@ -6,6 +6,13 @@ use tokio_stream::wrappers::BroadcastStream;
 use crate::ecam::{EcamDriver, EcamDriverOutput, EcamError};
 use crate::protocol::*;
 
+/// How long [`Ecam::request`] waits for a response before sending the request again.
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+/// How many times [`Ecam::request`] sends a request before giving up.
+const REQUEST_ATTEMPTS: usize = 3;
+/// The longest that [`Ecam::request`] waits before giving up, when every attempt goes unanswered.
+pub const MAX_REQUEST_DURATION: Duration = REQUEST_TIMEOUT.saturating_mul(REQUEST_ATTEMPTS as u32);
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum EcamStatus {
     StandBy,
@@ -23,6 +30,11 @@ pub enum EcamStatus {
 pub enum EcamOutput {
     Ready,
     Packet(EcamPacket<Response>),
+    /// The driver disconnected, and the [`Ecam`] is trying to reconnect (see [`ReconnectPolicy`]).
+    Disconnected,
+    /// The [`Ecam`] reconnected after [`EcamOutput::Disconnected`]. The new driver reports [`EcamOutput::Ready`] once
+    /// it is ready.
+    Reconnected,
     Done,
 }
 
@@ -53,8 +65,9 @@ impl From<EcamDriverOutput> for EcamOutput {
 impl From<EcamOutput> for EcamDriverOutput {
     fn from(other: EcamOutput) -> EcamDriverOutput {
         match other {
-            EcamOutput::Done => EcamDriverOutput::Done,
-            EcamOutput::Ready => EcamDriverOutput::Ready,
+            // Lifecycle events only exist above the driver, where a reconnection looks like the device going away
+            EcamOutput::Done | EcamOutput::Disconnected => EcamDriverOutput::Done,
+            EcamOutput::Ready | EcamOutput::Reconnected => EcamDriverOutput::Ready,
             EcamOutput::Packet(p) => EcamDriverOutput::Packet(p.into()),
         }
     }
@@ -101,6 +114,39 @@ impl EcamStatus {
     }
 }
 
+/// An event from [`Ecam::status_stream`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EcamStatusEvent {
+    /// The machine's [`EcamStatus`] changed, along with the full response it was extracted from.
+    Status(EcamStatus, MonitorV2Response),
+    /// The machine raised an alarm.
+    AlarmRaised(MachineEnum<EcamMachineAlarm>),
+    /// The machine cleared an alarm.
+    AlarmCleared(MachineEnum<EcamMachineAlarm>),
+    /// The connection to the machine dropped. The next status and any raised alarms are reported afresh.
+    Disconnected,
+    /// The connection to the machine was re-established (see [`ReconnectPolicy`]).
+    Reconnected,
+}
+
+impl EcamStatusEvent {
+    /// Computes the events between two consecutive monitor responses. With no previous response, the status and any
+    /// alarms that are already raised are reported.
+    fn diff(previous: Option<&MonitorV2Response>, next: &MonitorV2Response) -> Vec<Self> {
+        let status = EcamStatus::extract(next);
+        let mut events = vec![];
+        if previous.map(EcamStatus::extract) != Some(status) {
+            events.push(EcamStatusEvent::Status(status, next.clone()));
+        }
+        let previous_alarms = previous.map_or(0, |previous| previous.alarms.value);
+        let cleared = SwitchSet::<EcamMachineAlarm>::from_u16(previous_alarms & !next.alarms.value);
+        let raised = SwitchSet::<EcamMachineAlarm>::from_u16(next.alarms.value & !previous_alarms);
+        events.extend(cleared.set().into_iter().map(EcamStatusEvent::AlarmCleared));
+        events.extend(raised.set().into_iter().map(EcamStatusEvent::AlarmRaised));
+        events
+    }
+}
+
 struct StatusInterest {
     count: Arc<std::sync::Mutex<usize>>,
 }
@@ -173,12 +219,59 @@ impl Drop for EcamDropHandle {
     }
 }
 
+/// How an [`Ecam`] reconnects after its driver disconnects (ie: when the machine is power-cycled). Attempts are spaced
+/// by an exponential backoff, starting at `initial_backoff` and doubling up to `max_backoff`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ReconnectPolicy {
+    /// The delay before the first attempt.
+    pub initial_backoff: Duration,
+    /// The longest delay between attempts.
+    pub max_backoff: Duration,
+    /// The number of failed attempts before giving up, or `None` to keep trying for as long as the [`Ecam`] lives.
+    pub max_attempts: Option<usize>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+/// Creates a fresh driver for each reconnection attempt.
+pub(crate) type DriverFactory =
+    Arc<dyn Fn() -> AsyncFuture<'static, Box<dyn EcamDriver>> + Send + Sync>;
+
+/// Internal struct holding the current driver, which is replaced when the [`Ecam`] reconnects.
+#[derive(Clone)]
+struct DriverSlot(Arc<std::sync::Mutex<Arc<Box<dyn EcamDriver>>>>);
+
+impl DriverSlot {
+    fn new(driver: Box<dyn EcamDriver>) -> Self {
+        Self(Arc::new(std::sync::Mutex::new(Arc::new(driver))))
+    }
+
+    fn get(&self) -> Arc<Box<dyn EcamDriver>> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn replace(&self, driver: Box<dyn EcamDriver>) {
+        *self.0.lock().unwrap() = Arc::new(driver);
+    }
+}
+
 /// Handle that gives a user access to a machine. When all clones are dropped, the connection is closed.
 #[derive(Clone)]
 pub struct Ecam {
-    driver: Arc<Box<dyn EcamDriver>>,
+    driver: DriverSlot,
     internals: Arc<Mutex<EcamInternals>>,
     alive: Alive,
+    timeout: Option<Duration>,
+    /// Held while a request is waiting for its response, as responses are only told apart by their request ID.
+    request_lock: Arc<Mutex<()>>,
     #[allow(unused)]
     drop_handle: Arc<EcamDropHandle>,
 }
@@ -189,12 +282,29 @@ struct EcamInternals {
     ready_lock: Arc<tokio::sync::Semaphore>,
     status_interest: StatusInterest,
     dump_packets: bool,
+    sync_time: bool,
     started: bool,
 }
 
 impl Ecam {
+    /// Creates a new handle for the given driver. Use [`EcamBuilder`](super::EcamBuilder) to sync the clock, limit waits
+    /// or reconnect.
     pub async fn new(driver: Box<dyn EcamDriver>, dump_packets: bool) -> Self {
-        let driver = Arc::new(driver);
+        Self::new_reconnecting(driver, dump_packets, false, None, None).await
+    }
+
+    /// Creates a new handle like [`Ecam::new`]. If `sync_time` is set, the machine's clock is set to the host's local
+    /// time as soon as the driver is ready. If `timeout` is set, it limits how long any wait for the machine may take,
+    /// replacing the defaults of individual operations. If `reconnect` is given, the driver is replaced with one from
+    /// the factory whenever it disconnects.
+    pub(crate) async fn new_reconnecting(
+        driver: Box<dyn EcamDriver>,
+        dump_packets: bool,
+        sync_time: bool,
+        timeout: Option<Duration>,
+        reconnect: Option<(ReconnectPolicy, DriverFactory)>,
+    ) -> Self {
+        let driver = DriverSlot::new(driver);
         let (tx, rx) = tokio::sync::watch::channel(None);
         let (txb, _) = tokio::sync::broadcast::channel(100);
 
@@ -215,6 +325,7 @@ impl Ecam {
             status_interest: StatusInterest::new(),
             started: false,
             dump_packets,
+            sync_time,
         }));
         let alive = Alive::new();
         let ecam_result = Ecam {
@@ -224,6 +335,8 @@ impl Ecam {
                 alive: alive.clone(),
             }),
             alive,
+            timeout,
+            request_lock: Default::default(),
         };
 
         tokio::spawn(Self::operation_loop(
@@ -232,13 +345,13 @@ impl Ecam {
             ecam_result.driver.clone(),
             ecam_result.internals.clone(),
             ecam_result.alive.clone(),
+            reconnect,
         ));
-        let (driver, alive) = (ecam_result.driver.clone(), ecam_result.alive.clone());
-        tokio::spawn(Self::alive_watch(driver, alive));
         ecam_result
     }
 
-    async fn alive_watch(driver: Arc<Box<dyn EcamDriver>>, alive: Alive) -> Result<(), EcamError> {
+    /// Completes when the driver is no longer alive, or the [`Ecam`] is dropped.
+    async fn alive_watch(driver: Arc<Box<dyn EcamDriver>>, alive: Alive) {
         while let Ok(b) = driver.alive().await {
             if !alive.is_alive() || !b {
                 break;
@@ -247,27 +360,113 @@ impl Ecam {
             tokio::time::sleep(Duration::from_millis(10)).await;
         }
         trace_shutdown!("Ecam::alive_watch()");
-        alive.deaden();
-        Ok(())
     }
 
     async fn operation_loop(
         mut ready_lock_semaphore: Option<OwnedSemaphorePermit>,
         tx: tokio::sync::watch::Sender<Option<MonitorV2Response>>,
-        driver: Arc<Box<dyn EcamDriver>>,
+        driver: DriverSlot,
         internals: Arc<Mutex<EcamInternals>>,
         alive: Alive,
+        reconnect: Option<(ReconnectPolicy, DriverFactory)>,
+    ) -> Result<(), EcamError> {
+        let packet_tap_sender = internals.lock().await.packet_tap.clone();
+        while alive.is_alive() {
+            // Each connection gets its own monitor loop, which stops when the connection does
+            let connection = Alive::new();
+            let result = Self::connection_loop(
+                &mut ready_lock_semaphore,
+                &tx,
+                driver.get(),
+                &internals,
+                &alive,
+                &connection,
+            )
+            .await;
+            connection.deaden();
+            internals.lock().await.started = false;
+            if let Err(e) = result {
+                warning!("Connection failed: {:?}", e);
+            }
+            let Some((policy, factory)) = &reconnect else {
+                break;
+            };
+            if !alive.is_alive() {
+                break;
+            }
+            // The last status is stale until the new connection reports one, so block status waits until it does
+            tx.send_replace(None);
+            if ready_lock_semaphore.is_none() {
+                let ready_lock = internals.lock().await.ready_lock.clone();
+                ready_lock_semaphore = ready_lock.acquire_owned().await.ok();
+            }
+            let _ = packet_tap_sender.send(EcamOutput::Disconnected);
+            match Self::reconnect(policy, factory, &alive).await {
+                Some(new_driver) => {
+                    driver.replace(new_driver);
+                    let _ = packet_tap_sender.send(EcamOutput::Reconnected);
+                }
+                None => break,
+            }
+        }
+        let _ = packet_tap_sender.send(EcamOutput::Done);
+        trace_shutdown!("Ecam::operation_loop");
+        alive.deaden();
+        Ok(())
+    }
+
+    /// Creates a new driver, backing off between attempts. Returns `None` if the policy gives up or the [`Ecam`] is
+    /// dropped.
+    async fn reconnect(
+        policy: &ReconnectPolicy,
+        factory: &DriverFactory,
+        alive: &Alive,
+    ) -> Option<Box<dyn EcamDriver>> {
+        let mut backoff = policy.initial_backoff;
+        let mut attempt = 0;
+        while policy.max_attempts.map_or(true, |max| attempt < max) {
+            tokio::time::sleep(backoff).await;
+            if !alive.is_alive() {
+                return None;
+            }
+            attempt += 1;
+            info!("Reconnecting (attempt {})...", attempt);
+            match factory().await {
+                Ok(driver) => return Some(driver),
+                Err(e) => warning!("Failed to reconnect: {:?}", e),
+            }
+            backoff = (backoff * 2).min(policy.max_backoff);
+        }
+        info!("Giving up after {} attempts to reconnect", attempt);
+        None
+    }
+
+    /// Processes the output of one driver until it disconnects or the [`Ecam`] is dropped.
+    async fn connection_loop(
+        ready_lock_semaphore: &mut Option<OwnedSemaphorePermit>,
+        tx: &tokio::sync::watch::Sender<Option<MonitorV2Response>>,
+        driver: Arc<Box<dyn EcamDriver>>,
+        internals: &Arc<Mutex<EcamInternals>>,
+        alive: &Alive,
+        connection: &Alive,
     ) -> Result<(), EcamError> {
         let packet_tap_sender = internals.lock().await.packet_tap.clone();
         let dump_packets = internals.lock().await.dump_packets;
+        let sync_time = internals.lock().await.sync_time;
         let mut started = false;
+        let watch = Self::alive_watch(driver.clone(), alive.clone());
+        tokio::pin!(watch);
         while alive.is_alive() {
+            let output = tokio::select! {
+                output = driver.read() => output?,
+                _ = &mut watch => break,
+            };
             // Treat end-of-stream as EcamOutput::Done, but we might want to reconsider this in the future
-            let packet: EcamOutput = driver
-                .read()
-                .await?
-                .unwrap_or(EcamDriverOutput::Done)
-                .into();
+            let packet: EcamOutput = output.unwrap_or(EcamDriverOutput::Done).into();
+            if packet == EcamOutput::Done {
+                trace_shutdown!("Ecam::connection_loop (Done)");
+                break;
+            }
             let _ = packet_tap_sender.send(packet.clone());
             if dump_packets {
                 trace_packet!("{:?}", packet);
@@ -280,16 +479,20 @@ impl Ecam {
                         tokio::spawn(Self::write_monitor_loop(
                             driver.clone(),
                             internals.clone(),
-                            alive.clone(),
+                            connection.clone(),
                         ));
                         started = true;
                         internals.lock().await.started = true;
+                        if sync_time {
+                            let (hour, minute) = crate::util::local_time();
+                            let packet =
+                                EcamDriverPacket::from_vec(Request::SetTime(hour, minute).encode());
+                            if driver.write(packet).await.is_err() {
+                                warning!("Failed to set the time");
+                            }
+                        }
                     }
                 }
-                EcamOutput::Done => {
-                    trace_shutdown!("Ecam::operation_loop (Done)");
-                    break;
-                }
                 EcamOutput::Packet(EcamPacket {
                     representation: Some(Response::MonitorV2(x)),
                     ..
@@ -303,8 +506,7 @@ impl Ecam {
                 _ => {}
             }
         }
-        trace_shutdown!("Ecam::operation_loop");
-        alive.deaden();
+        trace_shutdown!("Ecam::connection_loop");
         Ok(())
     }
 
@@ -313,13 +515,30 @@ impl Ecam {
         self.alive.is_alive()
     }
 
+    /// The timeout for an operation whose own default timeout is `default`.
+    pub fn timeout_or(&self, default: Duration) -> Duration {
+        self.timeout.unwrap_or(default)
+    }
+
     /// Blocks until the device state reaches our desired state.
     pub async fn wait_for_state(
         &self,
         state: EcamStatus,
         monitor: fn(EcamStatus) -> (),
     ) -> Result<(), EcamError> {
-        self.wait_for(|status| state.matches(status), monitor).await
+        self.wait_for_until(|status| state.matches(status), monitor, self.timeout)
+            .await
+    }
+
+    /// Blocks until the device state reaches our desired state, failing with [`EcamError::Timeout`] after `timeout`.
+    pub async fn wait_for_state_timeout(
+        &self,
+        state: EcamStatus,
+        monitor: fn(EcamStatus) -> (),
+        timeout: Duration,
+    ) -> Result<(), EcamError> {
+        self.wait_for_until(|status| state.matches(status), monitor, Some(timeout))
+            .await
     }
 
     /// Blocks until the device state is not in the undesired state.
@@ -328,7 +547,19 @@ impl Ecam {
         state: EcamStatus,
         monitor: fn(EcamStatus) -> (),
     ) -> Result<(), EcamError> {
-        self.wait_for(|status| !state.matches(status), monitor)
+        self.wait_for_until(|status| !state.matches(status), monitor, self.timeout)
+            .await
+    }
+
+    /// Blocks until the device state is not in the undesired state, failing with [`EcamError::Timeout`] after
+    /// `timeout`.
+    pub async fn wait_for_not_state_timeout(
+        &self,
+        state: EcamStatus,
+        monitor: fn(EcamStatus) -> (),
+        timeout: Duration,
+    ) -> Result<(), EcamError> {
+        self.wait_for_until(|status| !state.matches(status), monitor, Some(timeout))
             .await
     }
 
@@ -337,32 +568,89 @@ impl Ecam {
     where
         F: Fn(&MonitorV2Response) -> bool,
     {
+        self.wait_for_until(f, monitor, self.timeout).await
+    }
+
+    /// Blocks until the state test function returns true, failing with [`EcamError::Timeout`] after `timeout`.
+    pub async fn wait_for_timeout<F>(
+        &self,
+        f: F,
+        monitor: fn(EcamStatus) -> (),
+        timeout: Duration,
+    ) -> Result<(), EcamError>
+    where
+        F: Fn(&MonitorV2Response) -> bool,
+    {
+        self.wait_for_until(f, monitor, Some(timeout)).await
+    }
+
+    async fn wait_for_until<F>(
+        &self,
+        f: F,
+        monitor: fn(EcamStatus) -> (),
+        timeout: Option<Duration>,
+    ) -> Result<(), EcamError>
+    where
+        F: Fn(&MonitorV2Response) -> bool,
+    {
+        let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
         let alive = self.alive.clone();
         let mut internals = self.internals.lock().await;
         let mut rx = internals.last_status.clone();
         let status_interest = internals.status_interest.lock();
         drop(internals);
+        let mut last = None;
         while alive.is_alive() {
             if let Some(test) = rx.borrow().as_ref() {
+                last = Some(EcamStatus::extract(test));
                 monitor(EcamStatus::extract(test));
                 if f(test) {
                     drop(status_interest);
                     return Ok(());
                 }
             }
-            // TODO: timeout
-            rx.changed().await.map_err(|_| EcamError::Unknown)?;
+            if let Some(deadline) = deadline {
+                tokio::time::timeout_at(deadline, rx.changed())
+                    .await
+                    .map_err(|_| EcamError::Timeout(last))?
+            } else {
+                rx.changed().await
+            }
+            .map_err(|_| EcamError::Disconnected)?;
         }
-        Err(EcamError::Unknown)
+        Err(EcamError::Disconnected)
     }
 
     /// Wait for the connection to establish, but not any particular state.
     pub async fn wait_for_connection(&self) -> Result<(), EcamError> {
-        let _ = self.current_state().await?;
+        if let Some(timeout) = self.timeout {
+            self.wait_for_connection_timeout(timeout).await
+        } else {
+            let _ = self.current_state().await?;
+            Ok(())
+        }
+    }
+
+    /// Wait for the connection to establish, failing with [`EcamError::Timeout`] after `timeout`.
+    pub async fn wait_for_connection_timeout(&self, timeout: Duration) -> Result<(), EcamError> {
+        let _ = tokio::time::timeout(timeout, self.current_state())
+            .await
+            .map_err(|_| EcamError::Timeout(None))??;
         Ok(())
     }
 
-    /// Returns the current state, or blocks if we don't know what the current state is yet.
+    /// Returns the last status received from the device, if any, without waiting for a new one.
+    pub async fn last_status(&self) -> Option<EcamStatus> {
+        let internals = self.internals.lock().await;
+        let status = internals
+            .last_status
+            .borrow()
+            .as_ref()
+            .map(EcamStatus::extract);
+        status
+    }
+
+    /// Returns the current state, or blocks if we don't know what the current state is yet (ie: while reconnecting).
     pub async fn current_state(&self) -> Result<EcamStatus, EcamError> {
         let mut internals = self.internals.lock().await;
         let status_interest = internals.status_interest.lock();
@@ -373,12 +661,12 @@ impl Ecam {
             ready_lock
                 .acquire_owned()
                 .await
-                .map_err(|_| EcamError::Unknown)?,
+                .map_err(|_| EcamError::Disconnected)?,
         );
         let ret = if let Some(test) = rx.borrow().as_ref() {
             Ok(EcamStatus::extract(test))
         } else {
-            Err(EcamError::Unknown)
+            Err(EcamError::Disconnected)
         };
         drop(status_interest);
         ret
@@ -390,7 +678,7 @@ impl Ecam {
             warning!("Packet sent before device was ready!");
         }
         drop(internals);
-        self.driver.write(packet.into()).await
+        self.driver.get().write(packet.into()).await
     }
 
     /// Convenience method to skip the EcamPacket.
@@ -398,20 +686,99 @@ impl Ecam {
         self.write(EcamPacket::from_represenation(r)).await
     }
 
+    /// Sends a request and waits for the device's response to it, which is the next response with the same
+    /// [`EcamRequestId`]. The request is sent again if no response arrives within a short time, and fails with
+    /// [`EcamError::Timeout`] if the device never answers. Requests made at the same time (ie: from clones of this
+    /// [`Ecam`]) are sent one at a time so that their responses can't be confused.
+    pub async fn request(&self, request: Request) -> Result<Response, EcamError> {
+        self.request_matching(request, Some).await
+    }
+
+    /// Sends a request like [`Ecam::request`], but only accepts a response that the given function maps to a value.
+    /// Other responses with the same [`EcamRequestId`] (ie: a late response to an earlier attempt for a different
+    /// beverage) are ignored.
+    pub async fn request_matching<T>(
+        &self,
+        request: Request,
+        f: impl Fn(Response) -> Option<T>,
+    ) -> Result<T, EcamError> {
+        let _lock = self.request_lock.lock().await;
+        let request_id = request.ecam_request_id() as u8;
+        let mut tap = self.packet_tap().await?;
+        for _ in 0..REQUEST_ATTEMPTS {
+            self.write_request(request.clone()).await?;
+            let deadline = tokio::time::Instant::now() + REQUEST_TIMEOUT;
+            while let Ok(output) = tokio::time::timeout_at(deadline, tap.next()).await {
+                match output {
+                    Some(EcamOutput::Done | EcamOutput::Disconnected) | None => {
+                        return Err(EcamError::Disconnected)
+                    }
+                    Some(EcamOutput::Packet(EcamPacket {
+                        representation,
+                        bytes,
+                    })) if bytes.bytes.first() == Some(&request_id) => match representation {
+                        Some(response) => {
+                            if let Some(value) = f(response) {
+                                return Ok(value);
+                            }
+                        }
+                        None => return Err(EcamError::Decode(bytes.bytes)),
+                    },
+                    Some(_) => {}
+                }
+            }
+        }
+        Err(EcamError::Timeout(self.last_status().await))
+    }
+
+    /// Streams changes in the machine's status, decoded from its monitor responses: an [`EcamStatusEvent::Status`]
+    /// whenever the [`EcamStatus`] changes, and an event for each alarm that is raised or cleared. The machine is polled
+    /// for its status for as long as the stream is held, and the stream ends when the [`Ecam`] does. Connection drops and
+    /// reconnections are reported as [`EcamStatusEvent::Disconnected`] and [`EcamStatusEvent::Reconnected`].
+    pub async fn status_stream(&self) -> Result<impl Stream<Item = EcamStatusEvent>, EcamError> {
+        let mut tap = self.packet_tap().await?;
+        let status_interest = self.internals.lock().await.status_interest.lock();
+        Ok(async_stream::stream! {
+            let _status_interest = status_interest;
+            let mut last = None;
+            while let Some(output) = tap.next().await {
+                match output {
+                    EcamOutput::Packet(EcamPacket {
+                        representation: Some(Response::MonitorV2(response)),
+                        ..
+                    }) => {
+                        for event in EcamStatusEvent::diff(last.as_ref(), &response) {
+                            yield event;
+                        }
+                        last = Some(response);
+                    }
+                    EcamOutput::Disconnected => {
+                        last = None;
+                        yield EcamStatusEvent::Disconnected;
+                    }
+                    EcamOutput::Reconnected => yield EcamStatusEvent::Reconnected,
+                    EcamOutput::Done => break,
+                    _ => {}
+                }
+            }
+        })
+    }
+
     pub async fn packet_tap(&self) -> Result<impl Stream<Item = EcamOutput>, EcamError> {
         let internals = self.internals.lock().await;
-        Ok(BroadcastStream::new(internals.packet_tap.subscribe())
-            .map(|x| x.expect("Unexpected receive error")))
+        // A lagging subscriber misses the packets it was too slow to receive, but keeps receiving later ones
+        Ok(BroadcastStream::new(internals.packet_tap.subscribe()).filter_map(|x| x.ok()))
     }
 
-    /// The monitor loop is booted when the underlying driver reports that it is ready.
+    /// The monitor loop is booted when the underlying driver reports that it is ready, and runs until that driver's
+    /// connection ends.
     async fn write_monitor_loop(
         driver: Arc<Box<dyn EcamDriver>>,
         internals: Arc<Mutex<EcamInternals>>,
-        alive: Alive,
+        connection: Alive,
     ) -> Result<(), EcamError> {
         let status_request = EcamDriverPacket::from_vec(Request::MonitorV2().encode());
-        while alive.is_alive() {
+        while connection.is_alive() {
             // Only send status update packets while there is status interest
             if internals.lock().await.status_interest.count() == 0 {
                 tokio::time::sleep(Duration::from_millis(100)).await;
@@ -436,7 +803,7 @@ impl Ecam {
             }
         }
         trace_shutdown!("Ecam::write_monitor_loop()");
-        alive.deaden();
+        connection.deaden();
         Ok(())
     }
 }
@@ -466,4 +833,306 @@ mod test {
             assert_eq!(status, expected_status);
         }
     }
+
+    #[tokio::test]
+    async fn wait_for_state_times_out() -> Result<(), EcamError> {
+        let ecam = crate::ecam::EcamBuilder::new(crate::ecam::DriverSelection::parse("sim")?)
+            .connect()
+            .await?;
+        let result = ecam
+            .wait_for_state_timeout(EcamStatus::Ready, |_| (), Duration::from_millis(500))
+            .await;
+        assert!(matches!(
+            result,
+            Err(EcamError::Timeout(Some(EcamStatus::StandBy)))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests() -> Result<(), EcamError> {
+        let ecam = crate::ecam::EcamBuilder::new(crate::ecam::DriverSelection::parse("sim[on]")?)
+            .connect()
+            .await?;
+        ecam.wait_for_connection().await?;
+        let beverage = EcamBeverageId::RegularCoffee;
+        let (recipe, min_max) = tokio::join!(
+            ecam.request(Request::RecipeQuantityRead(1, beverage.into())),
+            ecam.request(Request::RecipeMinMaxSync(beverage.into()))
+        );
+        assert!(matches!(recipe?, Response::RecipeQuantityRead(1, b, _) if b == beverage));
+        assert!(matches!(min_max?, Response::RecipeMinMaxSync(b, _) if b == beverage));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn request_times_out() -> Result<(), EcamError> {
+        let ecam = crate::ecam::EcamBuilder::new(crate::ecam::DriverSelection::parse("sim[on]")?)
+            .connect()
+            .await?;
+        ecam.wait_for_connection().await?;
+        // The simulator doesn't answer PIN requests
+        let result = ecam.request(Request::PinRead()).await;
+        assert!(matches!(result, Err(EcamError::Timeout(_))));
+        Ok(())
+    }
+
+    /// Collects lifecycle events from the tap until `last` arrives.
+    async fn lifecycle_until(
+        tap: &mut (impl Stream<Item = EcamOutput> + Unpin),
+        last: EcamOutput,
+    ) -> Vec<EcamOutput> {
+        let mut events = vec![];
+        while let Some(output) = tokio::time::timeout(Duration::from_secs(5), tap.next())
+            .await
+            .expect("Timed out waiting for lifecycle events")
+        {
+            if !matches!(output, EcamOutput::Packet(..)) {
+                events.push(output.clone());
+            }
+            if output == last {
+                break;
+            }
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_disconnect() -> Result<(), EcamError> {
+        let driver = crate::ecam::EcamReplay::from_recording("0 R: READY\n200 Q:\n")?;
+        let factory: DriverFactory = Arc::new(|| {
+            Box::pin(async {
+                Ok(Box::new(crate::ecam::get_ecam_simulator("sim[on]").await?)
+                    as Box<dyn EcamDriver>)
+            })
+        });
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let ecam = Ecam::new_reconnecting(
+            Box::new(driver),
+            false,
+            false,
+            None,
+            Some((policy, factory)),
+        )
+        .await;
+        let mut tap = ecam.packet_tap().await?;
+        let mut events = lifecycle_until(&mut tap, EcamOutput::Reconnected).await;
+        events.extend(lifecycle_until(&mut tap, EcamOutput::Ready).await);
+        assert_eq!(
+            events,
+            vec![
+                EcamOutput::Ready,
+                EcamOutput::Disconnected,
+                EcamOutput::Reconnected,
+                EcamOutput::Ready
+            ]
+        );
+        // The monitor loop restarts with the new driver
+        ecam.wait_for_state_timeout(EcamStatus::Ready, |_| (), Duration::from_secs(5))
+            .await?;
+        assert!(ecam.is_alive());
+        Ok(())
+    }
+
+    /// Wraps a driver, reporting that it has disconnected once `disconnected` is set.
+    struct Disconnectable {
+        driver: Box<dyn EcamDriver>,
+        disconnected: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    impl EcamDriver for Disconnectable {
+        fn read(&self) -> AsyncFuture<Option<EcamDriverOutput>> {
+            self.driver.read()
+        }
+
+        fn write(&self, data: EcamDriverPacket) -> AsyncFuture<()> {
+            self.driver.write(data)
+        }
+
+        fn alive(&self) -> AsyncFuture<bool> {
+            let disconnected = self.disconnected.load(std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async move { Ok(!disconnected) })
+        }
+
+        fn scan<'a>() -> AsyncFuture<'a, (String, uuid::Uuid)>
+        where
+            Self: Sized,
+        {
+            Box::pin(async { Err(EcamError::NotFound) })
+        }
+    }
+
+    #[tokio::test]
+    async fn disconnect_clears_status() -> Result<(), EcamError> {
+        let disconnected = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let driver = Disconnectable {
+            driver: Box::new(crate::ecam::get_ecam_simulator("sim[on]").await?),
+            disconnected: disconnected.clone(),
+        };
+        // Keep the reconnection pending for the rest of the test
+        let factory: DriverFactory = Arc::new(|| Box::pin(async { Err(EcamError::NotFound) }));
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let ecam = Ecam::new_reconnecting(
+            Box::new(driver),
+            false,
+            false,
+            None,
+            Some((policy, factory)),
+        )
+        .await;
+        assert_eq!(ecam.current_state().await?, EcamStatus::Ready);
+
+        let mut tap = ecam.packet_tap().await?;
+        disconnected.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(
+            lifecycle_until(&mut tap, EcamOutput::Disconnected).await,
+            vec![EcamOutput::Disconnected]
+        );
+        assert_eq!(ecam.last_status().await, None);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), ecam.current_state())
+                .await
+                .is_err(),
+            "current_state should wait for a new status after a disconnect"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reconnect_gives_up() -> Result<(), EcamError> {
+        let driver = crate::ecam::EcamReplay::from_recording("0 R: READY\n200 Q:\n")?;
+        let factory: DriverFactory = Arc::new(|| Box::pin(async { Err(EcamError::NotFound) }));
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(20),
+            max_attempts: Some(3),
+        };
+        let ecam = Ecam::new_reconnecting(
+            Box::new(driver),
+            false,
+            false,
+            None,
+            Some((policy, factory)),
+        )
+        .await;
+        let mut tap = ecam.packet_tap().await?;
+        assert_eq!(
+            lifecycle_until(&mut tap, EcamOutput::Done).await,
+            vec![
+                EcamOutput::Ready,
+                EcamOutput::Disconnected,
+                EcamOutput::Done
+            ]
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!ecam.is_alive());
+        Ok(())
+    }
+
+    #[test]
+    fn status_event_diff() {
+        let response = |state, alarms: &[EcamMachineAlarm]| MonitorV2Response {
+            state: MachineEnum::Value(state),
+            alarms: SwitchSet::of(alarms),
+            ..Default::default()
+        };
+        let ready = response(EcamMachineState::ReadyOrDispensing, &[]);
+        assert_eq!(
+            EcamStatusEvent::diff(None, &ready),
+            vec![EcamStatusEvent::Status(EcamStatus::Ready, ready.clone())]
+        );
+        assert_eq!(EcamStatusEvent::diff(Some(&ready), &ready), vec![]);
+
+        let empty = EcamMachineAlarm::EmptyWaterTank;
+        let full = EcamMachineAlarm::CoffeeWasteContainerFull;
+        let alarm = response(EcamMachineState::ReadyOrDispensing, &[empty]);
+        assert_eq!(
+            EcamStatusEvent::diff(Some(&ready), &alarm),
+            vec![
+                EcamStatusEvent::Status(EcamStatus::Alarm(empty.into()), alarm.clone()),
+                EcamStatusEvent::AlarmRaised(empty.into())
+            ]
+        );
+
+        let swapped = response(EcamMachineState::ReadyOrDispensing, &[full]);
+        assert_eq!(
+            EcamStatusEvent::diff(Some(&alarm), &swapped),
+            vec![
+                EcamStatusEvent::Status(EcamStatus::Alarm(full.into()), swapped.clone()),
+                EcamStatusEvent::AlarmCleared(empty.into()),
+                EcamStatusEvent::AlarmRaised(full.into())
+            ]
+        );
+        assert_eq!(
+            EcamStatusEvent::diff(Some(&swapped), &ready),
+            vec![
+                EcamStatusEvent::Status(EcamStatus::Ready, ready.clone()),
+                EcamStatusEvent::AlarmCleared(full.into())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn status_stream_reports_alarms() -> Result<(), EcamError> {
+        let ecam = crate::ecam::EcamBuilder::new(crate::ecam::DriverSelection::parse(
+            "sim[on,alarm=emptywatertank]",
+        )?)
+        .connect()
+        .await?;
+        let events: Vec<_> = tokio::time::timeout(
+            Duration::from_secs(5),
+            ecam.status_stream().await?.take(2).collect(),
+        )
+        .await
+        .map_err(|_| EcamError::Timeout(None))?;
+        let alarm = MachineEnum::Value(EcamMachineAlarm::EmptyWaterTank);
+        assert!(matches!(
+            events[0],
+            EcamStatusEvent::Status(EcamStatus::Alarm(a), _) if a == alarm
+        ));
+        assert_eq!(events[1], EcamStatusEvent::AlarmRaised(alarm));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn status_stream_reports_reconnects() -> Result<(), EcamError> {
+        let driver = crate::ecam::EcamReplay::from_recording("0 R: READY\n200 Q:\n")?;
+        let factory: DriverFactory = Arc::new(|| {
+            Box::pin(async {
+                Ok(Box::new(crate::ecam::get_ecam_simulator("sim[on]").await?)
+                    as Box<dyn EcamDriver>)
+            })
+        });
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let ecam = Ecam::new_reconnecting(
+            Box::new(driver),
+            false,
+            false,
+            None,
+            Some((policy, factory)),
+        )
+        .await;
+        let events: Vec<_> = tokio::time::timeout(
+            Duration::from_secs(5),
+            ecam.status_stream().await?.take(3).collect(),
+        )
+        .await
+        .map_err(|_| EcamError::Timeout(None))?;
+        assert_eq!(events[0], EcamStatusEvent::Disconnected);
+        assert_eq!(events[1], EcamStatusEvent::Reconnected);
+        assert!(matches!(
+            events[2],
+            EcamStatusEvent::Status(EcamStatus::Ready, _)
+        ));
+        Ok(())
+    }
 }
@@ -5,20 +5,34 @@ use crate::prelude::*;
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::protocol::{EcamMachineAlarm, MachineEnum};
+
+mod builder;
 mod driver;
 mod ecam_bt;
+mod ecam_record;
+mod ecam_replay;
+mod ecam_serial;
 mod ecam_simulate;
 mod ecam_subprocess;
+mod ecam_tcp;
 mod ecam_wrapper;
 mod packet_receiver;
 mod packet_stream;
 mod stdin_stream;
 
 pub use self::ecam_bt::EcamBT;
+pub use builder::{DriverSelection, EcamBuilder};
 pub use driver::{EcamDriver, EcamDriverOutput};
+pub use ecam_record::EcamRecord;
+pub use ecam_replay::EcamReplay;
+pub use ecam_serial::EcamSerial;
 pub use ecam_simulate::get_ecam_simulator;
 pub use ecam_subprocess::connect as get_ecam_subprocess;
-pub use ecam_wrapper::{Ecam, EcamOutput, EcamStatus};
+pub use ecam_tcp::{bridge, EcamTcp};
+pub use ecam_wrapper::{
+    Ecam, EcamOutput, EcamStatus, EcamStatusEvent, ReconnectPolicy, MAX_REQUEST_DURATION,
+};
 pub use packet_receiver::EcamPacketReceiver;
 pub use stdin_stream::pipe_stdin;
 
@@ -26,9 +40,13 @@ pub async fn ecam_scan() -> Result<(String, Uuid), EcamError> {
     EcamBT::scan().await
 }
 
+/// Connects to the given device (see [`DriverSelection::parse`]) in this process. Use [`EcamBuilder`] to sync the
+/// clock, record the session, limit waits or reconnect.
 pub async fn ecam_lookup(device_name: &str, dump_packets: bool) -> Result<Ecam, EcamError> {
-    let driver = Box::new(get_ecam_subprocess(device_name).await?);
-    Ok(Ecam::new(driver, dump_packets).await)
+    EcamBuilder::new(DriverSelection::parse(device_name)?)
+        .dump_packets(dump_packets)
+        .connect()
+        .await
 }
 
 #[derive(Error, Debug)]
@@ -39,6 +57,27 @@ pub enum EcamError {
     BTError(#[from] btleplug::Error),
     #[error(transparent)]
     IOError(#[from] std::io::Error),
-    #[error("Unknown error")]
-    Unknown,
+    #[error("timed out (last status {0:?})")]
+    Timeout(Option<EcamStatus>),
+    /// The requested ingredients don't match the machine's recipe.
+    #[error("invalid ingredients: {0}")]
+    IngredientCheck(crate::operations::IngredientCheckError),
+    /// The machine isn't in a state that allows the operation.
+    #[error("machine is in state {0:?}")]
+    WrongState(EcamStatus),
+    /// The machine raised an alarm during the operation.
+    #[error("machine raised alarm {0:?}")]
+    Alarm(MachineEnum<EcamMachineAlarm>),
+    /// The device sent a response that couldn't be decoded.
+    #[error("failed to decode packet {}", crate::protocol::trace_hexdump(.0))]
+    Decode(Vec<u8>),
+    /// The device disconnected.
+    #[error("disconnected")]
+    Disconnected,
+    /// An argument or configuration value was rejected before anything was sent to the machine.
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    /// The machine didn't apply a change, as determined by reading it back.
+    #[error("not accepted by the machine: {0}")]
+    NotAccepted(String),
 }
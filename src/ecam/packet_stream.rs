@@ -3,7 +3,7 @@ use crate::prelude::*;
 use async_stream::stream;
 use futures::{Stream, StreamExt};
 
-use crate::protocol::{checksum, hexdump};
+use crate::protocol::{checksum, trace_hexdump};
 
 const SYNC_BYTE: u8 = 0xd0;
 /// Minimum packet length is four: length, one data byte, two bytes of checksum (sync byte doesn't count for length).
@@ -92,7 +92,7 @@ where
     stream! {
         let mut p = PacketBuilder::new();
         while let Some(m) = n.next().await {
-            trace_packet!("{{device->host}} {}", hexdump(&m));
+            trace_packet!("{{device->host}} {}", trace_hexdump(&m));
             if let Some(v) = p.accumulate(&m) {
                 yield v;
             }
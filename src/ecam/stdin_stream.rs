@@ -10,7 +10,7 @@ use crate::protocol::EcamDriverPacket;
 use super::{EcamDriver, EcamDriverOutput, EcamError};
 
 /// Converts a stdio line to an EcamDriverOutput.
-fn parse_line(s: &str) -> Option<EcamDriverOutput> {
+pub(super) fn parse_line(s: &str) -> Option<EcamDriverOutput> {
     if s == "R: READY" {
         Some(EcamDriverOutput::Ready)
     } else if let Some(s) = s.strip_prefix("S: ") {
@@ -26,8 +26,23 @@ fn parse_line(s: &str) -> Option<EcamDriverOutput> {
     }
 }
 
+/// Converts a line produced by [`to_line`] back to an EcamDriverOutput.
+pub(super) fn parse_output_line(s: &str) -> Option<EcamDriverOutput> {
+    if s == "R: READY" {
+        Some(EcamDriverOutput::Ready)
+    } else if let Some(s) = s.strip_prefix("R: ") {
+        Some(EcamDriverOutput::Packet(EcamDriverPacket::from_vec(
+            hex::decode(s).ok()?,
+        )))
+    } else if s.starts_with("Q:") {
+        Some(EcamDriverOutput::Done)
+    } else {
+        None
+    }
+}
+
 /// Converts an EcamDriverOutput to a stdio line.
-fn to_line(output: EcamDriverOutput) -> String {
+pub(super) fn to_line(output: EcamDriverOutput) -> String {
     match output {
         EcamDriverOutput::Ready => "R: READY".to_owned(),
         EcamDriverOutput::Done => "Q:".to_owned(),
@@ -81,11 +96,9 @@ macro_rules! spawn_loop {
 }
 
 /// Pipes an EcamDriver to/from stdio.
-pub async fn pipe_stdin<T: EcamDriver + 'static>(
-    ecam: T,
-) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn pipe_stdin(ecam: Box<dyn EcamDriver>) -> Result<(), Box<dyn std::error::Error>> {
     let mut bt_out = Box::pin(packet_stdio_stream());
-    let ecam = Arc::new(Box::new(ecam));
+    let ecam = Arc::new(ecam);
     let (tx, rx) = std::sync::mpsc::sync_channel(1);
 
     // Watchdog timer: if we don't get _some_ event within the timeout, we assume that things havegone sideways
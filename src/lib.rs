@@ -34,6 +34,48 @@
 //! Brewing RegularCoffee...
 //! ```
 //!
+//! Save a recipe to one of the custom slots of a profile, making that profile active on the machine:
+//!
+//! ```console
+//! $ longshot save-recipe --device-name (device) --profile 2 --select-profile --beverage custom01 --coffee 120 --taste strong --allow-defaults
+//! ```
+//!
+//! Serve a REST and WebSocket API for the device, keeping a single connection open for multiple clients:
+//!
+//! ```console
+//! $ longshot serve --device-name (device) --bind 127.0.0.1:8080
+//! Listening on http://127.0.0.1:8080
+//! ```
+//!
+//! Keep serving while the machine is power-cycled, reconnecting whenever the connection drops:
+//!
+//! ```console
+//! $ longshot serve --device-name (device) --bind 127.0.0.1:8080 --reconnect
+//! ```
+//!
+//! Connect to a machine whose service port is wired to a USB-serial adapter, instead of over Bluetooth:
+//!
+//! ```console
+//! $ longshot monitor --device-name serial:/dev/ttyUSB0
+//! ```
+//!
+//! Run a bridge on a machine within Bluetooth range of the device, and connect to it from elsewhere over TCP. The bridge
+//! is **unauthenticated and unencrypted**, so anyone who can reach it can control the machine: keep it listening on
+//! localhost and reach it through an SSH tunnel. Only one client can use the bridge at a time.
+//!
+//! ```console
+//! $ longshot bridge --device-name (device) --listen 127.0.0.1:8888
+//! $ ssh -N -L 8888:127.0.0.1:8888 (bridge-host) &
+//! $ longshot monitor --device-name tcp://127.0.0.1:8888
+//! ```
+//!
+//! Record the packets exchanged with the device, and play them back later without the device:
+//!
+//! ```console
+//! $ longshot list-recipes --device-name (device) --record session.txt
+//! $ longshot list-recipes --device-name replay:session.txt
+//! ```
+//!
 //! # API Examples
 //!
 //! Brew a long coffee with 250 impulses of water (approximately the size of an average North American coffee mug, or slightly more).
@@ -54,6 +96,28 @@
 //! # };
 //! ```
 //!
+//! Watch the machine's status, and the alarms it raises and clears, until the connection ends.
+//!
+//! ```no_run
+//! # use longshot::ecam::*;
+//! # use tokio_stream::StreamExt;
+//! # let _ = async {
+//! # let device_name = "00000000-0000-0000-0000-000000000000";
+//! let ecam = ecam_lookup(device_name, false).await?;
+//! let mut events = Box::pin(ecam.status_stream().await?);
+//! while let Some(event) = events.next().await {
+//!     match event {
+//!         EcamStatusEvent::Status(status, _) => println!("Status: {:?}", status),
+//!         EcamStatusEvent::AlarmRaised(alarm) => println!("Alarm raised: {:?}", alarm),
+//!         EcamStatusEvent::AlarmCleared(alarm) => println!("Alarm cleared: {:?}", alarm),
+//!         EcamStatusEvent::Disconnected => println!("Disconnected"),
+//!         EcamStatusEvent::Reconnected => println!("Reconnected"),
+//!     }
+//! }
+//! # Result::<(), EcamError>::Ok(())
+//! # };
+//! ```
+//!
 //! # Demo
 //!
 //! ![Demo of brewing a cappuccino](https://user-images.githubusercontent.com/512240/200137316-a09304e8-b34a-41ff-a847-af71af521ef8.gif)
@@ -1,14 +1,16 @@
 #![warn(clippy::all)]
 use clap::builder::{PossibleValue, PossibleValuesParser};
 use clap::{arg, command, Arg, ArgMatches};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
 
 mod app;
 
 use longshot::ecam::{
-    ecam_lookup, ecam_scan, get_ecam_simulator, pipe_stdin, Ecam, EcamBT, EcamError,
+    bridge, ecam_scan, pipe_stdin, DriverSelection, Ecam, EcamBuilder, EcamError, ReconnectPolicy,
 };
 use longshot::{operations::*, protocol::*};
-use uuid::Uuid;
 
 fn enum_value_parser<T: MachineEnumerable<T> + 'static>() -> PossibleValuesParser {
     PossibleValuesParser::new(T::all().map(|x| PossibleValue::new(x.to_arg_string())))
@@ -19,10 +21,14 @@ struct DeviceCommon {
     dump_packets: bool,
     turn_on: bool,
     allow_off: bool,
+    sync_time: bool,
+    record: Option<PathBuf>,
+    timeout: Option<Duration>,
+    reconnect: bool,
 }
 
 impl DeviceCommon {
-    fn args() -> [Arg; 4] {
+    fn args() -> [Arg; 7] {
         [
             arg!(--"device-name" <name>)
                 .help("Provides the name of the device")
@@ -35,6 +41,12 @@ impl DeviceCommon {
                 .hide(true)
                 .help("Allow brewing while machine is off")
                 .conflicts_with("turn-on"),
+            arg!(--"sync-time").help("Set the machine's clock to the local time when connecting"),
+            arg!(--"record" <file>)
+                .help("Records the packets exchanged with the device to a file (replay with --device-name replay:<file>)")
+                .value_parser(clap::value_parser!(PathBuf)),
+            arg!(--"reconnect")
+                .help("Reconnects to the device if the connection drops (ie: when the machine is power-cycled)"),
         ]
     }
 
@@ -47,13 +59,92 @@ impl DeviceCommon {
             dump_packets: cmd.get_flag("dump-packets"),
             turn_on: cmd.get_flag("turn-on"),
             allow_off: cmd.get_flag("allow-off"),
+            sync_time: cmd.get_flag("sync-time"),
+            record: cmd.get_one::<PathBuf>("record").cloned(),
+            timeout: cmd
+                .get_one::<u64>("timeout")
+                .copied()
+                .map(Duration::from_secs),
+            reconnect: cmd.get_flag("reconnect"),
         }
     }
+
+    /// Creates a builder for the device, running its driver in a subprocess.
+    fn builder(&self) -> Result<EcamBuilder, EcamError> {
+        let mut builder = EcamBuilder::new(DriverSelection::parse(&self.device_name)?)
+            .subprocess(true)
+            .dump_packets(self.dump_packets)
+            .sync_time(self.sync_time);
+        if let Some(record) = &self.record {
+            builder = builder.record(record);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if self.reconnect {
+            builder = builder.reconnect(ReconnectPolicy::default());
+        }
+        Ok(builder)
+    }
+}
+
+/// Ingredient arguments shared by commands that send a recipe to the machine.
+struct IngredientArgs {
+    ingredients: Vec<BrewIngredientInfo>,
+    mode: IngredientCheckMode,
+}
+
+impl IngredientArgs {
+    fn args() -> [Arg; 7] {
+        [
+            arg!(--"coffee" <amount>)
+                .help("Amount of coffee to brew")
+                .value_parser(0..=2500),
+            arg!(--"milk" <amount>)
+                .help("Amount of milk to steam/pour")
+                .value_parser(0..=2500),
+            arg!(--"hotwater" <amount>)
+                .help("Amount of hot water to pour")
+                .value_parser(0..=2500),
+            arg!(--"taste" <taste>)
+                .help("The strength of the beverage")
+                .value_parser(enum_value_parser::<EcamBeverageTaste>()),
+            arg!(--"temperature" <temperature>)
+                .help("The temperature of the beverage")
+                .value_parser(enum_value_parser::<EcamTemperature>()),
+            arg!(--"allow-defaults").help("Allow brewing if some parameters are not specified"),
+            arg!(--"force").help("Allow brewing with parameters that do not validate"),
+        ]
+    }
+
+    /// Parses the ingredient arguments, returning `None` (after reporting the problem) if any of them are invalid.
+    fn parse(cmd: &ArgMatches) -> Option<Self> {
+        let mut ingredients = vec![];
+        for arg in ["coffee", "milk", "hotwater", "taste", "temperature"] {
+            if let Some(value) = cmd.get_raw(arg) {
+                // Once clap has had a chance to validate the args, we go back to the underlying OsStr to parse it
+                let value = value.into_iter().next().unwrap().to_str().unwrap();
+                if let Some(ingredient) = BrewIngredientInfo::from_arg(arg, value) {
+                    ingredients.push(ingredient);
+                } else {
+                    eprintln!("Invalid value '{}' for argument '{}'", value, arg);
+                    return None;
+                }
+            }
+        }
+
+        let mode = match (cmd.get_flag("allow-defaults"), cmd.get_flag("force")) {
+            (_, true) => IngredientCheckMode::Force,
+            (true, false) => IngredientCheckMode::AllowDefaults,
+            (false, false) => IngredientCheckMode::Strict,
+        };
+        Some(Self { ingredients, mode })
+    }
 }
 
 async fn ecam(cmd: &ArgMatches, allow_off_and_alarms: bool) -> Result<Ecam, EcamError> {
     let device_common = DeviceCommon::parse(cmd);
-    let ecam = ecam_lookup(&device_common.device_name, device_common.dump_packets).await?;
+    let ecam = device_common.builder()?.connect().await?;
     if !power_on(
         ecam.clone(),
         device_common.allow_off | allow_off_and_alarms,
@@ -68,6 +159,42 @@ async fn ecam(cmd: &ArgMatches, allow_off_and_alarms: bool) -> Result<Ecam, Ecam
     Ok(ecam)
 }
 
+/// Resolves the `--profile` argument, which may be either a profile number or name.
+async fn profile(cmd: &ArgMatches, ecam: &Ecam) -> Result<u8, EcamError> {
+    if let Some(profile) = cmd.get_one::<String>("profile") {
+        resolve_profile(ecam.clone(), profile).await
+    } else {
+        Ok(DEFAULT_PROFILE)
+    }
+}
+
+/// Resolves the `--beverage` argument, which may be either a beverage or the name of a custom recipe.
+async fn beverage(cmd: &ArgMatches, ecam: &Ecam) -> Result<EcamBeverageId, EcamError> {
+    let beverage = cmd
+        .get_one::<String>("beverage")
+        .expect("Beverage required");
+    resolve_beverage(ecam.clone(), beverage).await
+}
+
+/// Resolves the `--beans` argument, which may be either a bean profile number or name.
+async fn bean_system(cmd: &ArgMatches, ecam: &Ecam) -> Result<u8, EcamError> {
+    let beans = cmd
+        .get_one::<String>("beans")
+        .expect("Bean profile required");
+    resolve_bean_system(ecam.clone(), beans).await
+}
+
+/// Reads a line from stdin without echoing it, prompting first if stdin is a terminal. Secrets are never accepted as
+/// command-line arguments, as those end up in shell history and process listings.
+fn read_secret(prompt: &str) -> std::io::Result<String> {
+    if atty::is(atty::Stream::Stdin) {
+        return Ok(rpassword::prompt_password(prompt)?.trim().to_owned());
+    }
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_owned())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     pretty_env_logger::init();
@@ -75,77 +202,283 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let matches = command!()
         .arg(arg!(--"trace").help("Trace packets to/from device"))
+        .arg(
+            arg!(--"timeout" <seconds>)
+                .help("Give up waiting for the machine after this many seconds (defaults depend on the operation)")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .global(true),
+        )
         .subcommand(
             command!("brew")
                 .about("Brew a coffee")
-                .args(&DeviceCommon::args())
+                .args(DeviceCommon::args())
                 .arg(
                     arg!(--"beverage" <name>)
                         .required(true)
-                        .help("The beverage to brew")
-                        .value_parser(enum_value_parser::<EcamBeverageId>()),
+                        .help("The beverage to brew, or the name of a custom recipe"),
                 )
+                .args(IngredientArgs::args())
                 .arg(
-                    arg!(--"coffee" <amount>)
-                        .help("Amount of coffee to brew")
-                        .value_parser(0..=2500),
+                    arg!(--"profile" <profile>)
+                        .help("The profile number or name whose recipe quantities are used"),
                 )
                 .arg(
-                    arg!(--"milk" <amount>)
-                        .help("Amount of milk to steam/pour")
-                        .value_parser(0..=2500),
-                )
+                    arg!(--"skip-brew")
+                        .hide(true)
+                        .help("Does everything except actually brew the beverage"),
+                ),
+        )
+        .subcommand(
+            command!("save-recipe")
+                .about("Save a recipe to one of the machine's profiles")
+                .args(DeviceCommon::args())
                 .arg(
-                    arg!(--"hotwater" <amount>)
-                        .help("Amount of hot water to pour")
-                        .value_parser(0..=2500),
+                    arg!(--"beverage" <name>)
+                        .required(true)
+                        .help("The beverage to save (including custom01 through custom10), or the name of a custom recipe"),
                 )
+                .args(IngredientArgs::args())
                 .arg(
-                    arg!(--"taste" <taste>)
-                        .help("The strength of the beverage")
-                        .value_parser(enum_value_parser::<EcamBeverageTaste>()),
+                    arg!(--"profile" <profile>)
+                        .help("The profile number or name to save the recipe to"),
                 )
                 .arg(
-                    arg!(--"temperature" <temperature>)
-                        .help("The temperature of the beverage")
-                        .value_parser(enum_value_parser::<EcamTemperature>()),
-                )
+                    arg!(--"select-profile")
+                        .help("Make the profile active before saving (the machine stays on this profile afterwards)"),
+                ),
+        )
+        .subcommand(
+            command!("stop")
+                .about("Stop brewing a beverage")
+                .args(DeviceCommon::args())
                 .arg(
-                    arg!(--"allow-defaults")
-                        .help("Allow brewing if some parameters are not specified"),
+                    arg!(--"beverage" <name>)
+                        .required(true)
+                        .help("The beverage being brewed, or the name of a custom recipe"),
+                ),
+        )
+        .subcommand(
+            command!("power")
+                .about("Turn the device on or off")
+                .subcommand_required(true)
+                .subcommand(
+                    command!("on")
+                        .about("Turn the device on and wait for it to be ready")
+                        .args(DeviceCommon::args()),
                 )
-                .arg(arg!(--"force").help("Allow brewing with parameters that do not validate"))
-                .arg(
-                    arg!(--"skip-brew")
-                        .hide(true)
-                        .help("Does everything except actually brew the beverage"),
+                .subcommand(
+                    command!("off")
+                        .about("Turn the device off and wait for it to return to standby")
+                        .args(DeviceCommon::args()),
                 ),
         )
         .subcommand(
             command!("monitor")
                 .about("Monitor the status of the device")
-                .args(&DeviceCommon::args()),
+                .args(DeviceCommon::args()),
         )
         .subcommand(
             command!("read-parameter")
                 .about("Read a parameter from the device")
-                .args(&DeviceCommon::args())
+                .args(DeviceCommon::args())
                 .arg(arg!(--"parameter" <parameter>).help("The parameter ID"))
                 .arg(arg!(--"length" <length>).help("The parameter length")),
         )
+        .subcommand(
+            command!("write-parameter")
+                .about("Write a parameter to the device, verifying it by reading it back")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"parameter" <parameter>)
+                        .required(true)
+                        .help("The parameter ID"),
+                )
+                .arg(
+                    arg!(--"value" <value>)
+                        .required(true)
+                        .help("The parameter value, as hex bytes"),
+                ),
+        )
         .subcommand(
             command!("list-recipes")
                 .about("List recipes stored in the device")
-                .args(&DeviceCommon::args())
+                .args(DeviceCommon::args())
                 .arg(arg!(--"detail").help("Show detailed ingredient information"))
-                .arg(arg!(--"raw").help("Show raw ingredient information")),
+                .arg(arg!(--"raw").help("Show raw ingredient information"))
+                .arg(
+                    arg!(--"profile" <profile>)
+                        .help("The profile number or name whose recipe quantities are listed"),
+                ),
+        )
+        .subcommand(
+            command!("list-profiles")
+                .about("List user profiles stored in the device")
+                .args(DeviceCommon::args()),
+        )
+        .subcommand(
+            command!("select-profile")
+                .about("Select the active user profile")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"profile" <profile>)
+                        .required(true)
+                        .help("The profile number or name"),
+                ),
+        )
+        .subcommand(
+            command!("rename-profile")
+                .about("Rename a user profile")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"profile" <profile>)
+                        .required(true)
+                        .help("The profile number or name"),
+                )
+                .arg(
+                    arg!(--"name" <name>)
+                        .required(true)
+                        .help("The new name of the profile (up to ten characters)"),
+                )
+                .arg(
+                    arg!(--"icon" <icon>)
+                        .help("The new icon of the profile (defaults to the current icon)"),
+                ),
+        )
+        .subcommand(
+            command!("rename-recipe")
+                .about("Rename a custom recipe")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"beverage" <name>)
+                        .required(true)
+                        .help("The custom recipe (custom01 through custom10), or its current name"),
+                )
+                .arg(
+                    arg!(--"name" <name>)
+                        .required(true)
+                        .help("The new name of the recipe (up to ten characters)"),
+                )
+                .arg(
+                    arg!(--"icon" <icon>)
+                        .help("The new icon of the recipe (defaults to the current icon)"),
+                ),
+        )
+        .subcommand(
+            command!("sync-time")
+                .about("Set the device's clock to the local time")
+                .args(DeviceCommon::args()),
+        )
+        .subcommand(
+            command!("beans")
+                .about("Manage the bean profiles stored in the device")
+                .subcommand_required(true)
+                .subcommand(
+                    command!("list")
+                        .about("List the bean profiles stored in the device")
+                        .args(DeviceCommon::args()),
+                )
+                .subcommand(
+                    command!("select")
+                        .about("Select the active bean profile")
+                        .args(DeviceCommon::args())
+                        .arg(
+                            arg!(--"beans" <beans>)
+                                .required(true)
+                                .help("The bean profile number or name"),
+                        ),
+                ),
+        )
+        .subcommand(
+            command!("pin")
+                .about("Manage the device's PIN lock")
+                .subcommand_required(true)
+                .subcommand(
+                    command!("status")
+                        .about("Show whether the PIN lock is enabled")
+                        .args(DeviceCommon::args()),
+                )
+                .subcommand(
+                    command!("set")
+                        .about("Enable the PIN lock, reading the four-digit PIN from the terminal or stdin")
+                        .args(DeviceCommon::args()),
+                )
+                .subcommand(
+                    command!("clear")
+                        .about("Disable the PIN lock")
+                        .args(DeviceCommon::args()),
+                ),
+        )
+        .subcommand(
+            command!("favorites")
+                .about("Manage the beverage ordering on the device's home screen")
+                .subcommand_required(true)
+                .subcommand(
+                    command!("list")
+                        .about("List the beverage ordering for the active profile")
+                        .args(DeviceCommon::args())
+                        .arg(
+                            arg!(--"profile" <profile>)
+                                .help("Make this profile active and list its ordering (it stays active afterwards)"),
+                        )
+                        .arg(
+                            arg!(--"all-profiles")
+                                .help("Make each profile active in turn and list its ordering (the last stays active afterwards)")
+                                .conflicts_with("profile"),
+                        ),
+                )
+                .subcommand(
+                    command!("set")
+                        .about("Set the beverage ordering for a profile")
+                        .args(DeviceCommon::args())
+                        .arg(
+                            arg!(--"profile" <profile>)
+                                .help("The profile number or name"),
+                        )
+                        .arg(
+                            arg!(--"select-profile")
+                                .help("Make the profile active before setting its ordering (it stays active afterwards)"),
+                        )
+                        .arg(
+                            arg!(--"beverages" <beverages>)
+                                .required(true)
+                                .help("A comma-separated list of beverages or custom recipe names, in order"),
+                        ),
+                ),
+        )
+        .subcommand(
+            command!("statistics")
+                .about("Show raw usage counters stored in the device, by identifier (counters are not decoded)")
+                .args(DeviceCommon::args())
+                .arg(arg!(--"start" <id>).help("The identifier of the first counter to read").required(true))
+                .arg(arg!(--"count" <count>).help("The number of counters to read").required(true))
+                .arg(arg!(--"json").help("Print the counters as JSON")),
         )
         .subcommand(command!("list").about("List all supported devices"))
+        .subcommand(
+            command!("serve")
+                .about("Serve an HTTP and WebSocket API for the device")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"bind" <address>)
+                        .help("The address to listen on")
+                        .default_value("127.0.0.1:8080"),
+                ),
+        )
+        .subcommand(
+            command!("bridge")
+                .about("Forward the device over TCP, for clients connecting with --device-name tcp://<host>:<port>")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"listen" <address>)
+                        .help("The address to listen on (ie: 127.0.0.1:8888). The bridge is unauthenticated, so don't expose it to untrusted networks")
+                        .required(true),
+                ),
+        )
         .subcommand(
             command!("x-internal-pipe")
                 .about("Used to communicate with the device")
                 .hide(true)
-                .args(&DeviceCommon::args()),
+                .args(DeviceCommon::args()),
         )
         .get_matches();
 
@@ -157,37 +490,60 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match subcommand {
         Some(("brew", cmd)) => {
             let skip_brew = cmd.get_flag("skip-brew");
-            let allow_defaults = cmd.get_flag("allow-defaults");
-            let force = cmd.get_flag("force");
-
-            let beverage: EcamBeverageId = EcamBeverageId::lookup_by_name_case_insensitive(
-                cmd.get_one::<String>("beverage").unwrap(),
-            )
-            .expect("Beverage required");
-
-            let mut ingredients = vec![];
-            for arg in ["coffee", "milk", "hotwater", "taste", "temperature"] {
-                if let Some(value) = cmd.get_raw(arg) {
-                    // Once clap has had a chance to validate the args, we go back to the underlying OsStr to parse it
-                    let value = value.into_iter().next().unwrap().to_str().unwrap();
-                    if let Some(ingredient) = BrewIngredientInfo::from_arg(arg, value) {
-                        ingredients.push(ingredient);
-                    } else {
-                        eprintln!("Invalid value '{}' for argument '{}'", value, arg);
-                        return Ok(());
+            let IngredientArgs { ingredients, mode } = match IngredientArgs::parse(cmd) {
+                Some(args) => args,
+                None => return Ok(()),
+            };
+
+            let ecam = ecam(cmd, false).await?;
+            let beverage = beverage(cmd, &ecam).await?;
+            let profile = profile(cmd, &ecam).await?;
+            let recipe =
+                validate_brew_for_profile(ecam.clone(), profile, beverage, ingredients, mode)
+                    .await?;
+            tokio::select! {
+                res = brew(ecam.clone(), skip_brew, beverage, recipe) => res?,
+                _ = tokio::signal::ctrl_c() => {
+                    longshot::info!("Stopping {:?} (press Ctrl+C again to exit immediately)...", beverage);
+                    tokio::select! {
+                        res = stop_brew(ecam.clone(), beverage) => res?,
+                        _ = tokio::signal::ctrl_c() => {}
                     }
                 }
             }
-
-            let mode = match (allow_defaults, force) {
-                (_, true) => IngredientCheckMode::Force,
-                (true, false) => IngredientCheckMode::AllowDefaults,
-                (false, false) => IngredientCheckMode::Strict,
+        }
+        Some(("save-recipe", cmd)) => {
+            let IngredientArgs { ingredients, mode } = match IngredientArgs::parse(cmd) {
+                Some(args) => args,
+                None => return Ok(()),
             };
-            let ecam = ecam(cmd, false).await?;
-            let recipe = validate_brew(ecam.clone(), beverage, ingredients, mode).await?;
-            brew(ecam.clone(), skip_brew, beverage, recipe).await?;
+
+            let ecam = ecam(cmd, true).await?;
+            let beverage = beverage(cmd, &ecam).await?;
+            let profile = profile(cmd, &ecam).await?;
+            if cmd.get_flag("select-profile") {
+                select_profile(ecam.clone(), profile).await?;
+            }
+            save_recipe(ecam, profile, beverage, ingredients, mode).await?;
+        }
+        Some(("stop", cmd)) => {
+            let ecam = ecam(cmd, true).await?;
+            let beverage = beverage(cmd, &ecam).await?;
+            stop_brew(ecam, beverage).await?;
         }
+        Some(("power", cmd)) => match cmd.subcommand() {
+            Some(("on", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                if !power_on(ecam.clone(), false, false, true).await? {
+                    return Err(EcamError::WrongState(ecam.current_state().await?).into());
+                }
+            }
+            Some(("off", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                power_off(ecam).await?;
+            }
+            _ => unreachable!(),
+        },
         Some(("monitor", cmd)) => {
             let ecam = ecam(cmd, true).await?;
             monitor(ecam).await?;
@@ -200,12 +556,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let ecam = ecam(cmd, true).await?;
             let detailed = cmd.get_flag("detail");
             let raw = cmd.get_flag("raw");
+            let profile = profile(cmd, &ecam).await?;
             if detailed {
-                list_recipes_detailed(ecam).await?;
+                list_recipes_detailed_for_profile(ecam, profile).await?;
             } else if raw {
-                list_recipes_raw(ecam).await?;
+                list_recipes_raw_for_profile(ecam, profile).await?;
             } else {
-                list_recipes(ecam).await?;
+                list_recipes_for_profile(ecam, profile).await?;
             }
         }
         Some(("read-parameter", cmd)) => {
@@ -220,16 +577,165 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let ecam = ecam(cmd, true).await?;
             read_parameter(ecam, parameter, length).await?;
         }
-        Some(("x-internal-pipe", cmd)) => {
-            let device_name = DeviceCommon::parse(cmd).device_name;
-            if device_name.starts_with("sim") {
-                let ecam = get_ecam_simulator(&device_name).await?;
-                pipe_stdin(ecam).await?;
+        Some(("write-parameter", cmd)) => {
+            let parameter = cmd
+                .get_one::<String>("parameter")
+                .map(|s| s.parse::<u16>().expect("Invalid number"))
+                .expect("Required");
+            let value = cmd.get_one::<String>("value").expect("Required");
+            let value = hex::decode(value).map_err(|_| {
+                EcamError::InvalidArgument(format!("invalid hex value '{}'", value))
+            })?;
+            let ecam = ecam(cmd, true).await?;
+            write_parameter(ecam, parameter, value.clone()).await?;
+            longshot::info!("Parameter {} set to {}", parameter, hex::encode(value));
+        }
+        Some(("list-profiles", cmd)) => {
+            let ecam = ecam(cmd, true).await?;
+            list_profiles(ecam).await?;
+        }
+        Some(("select-profile", cmd)) => {
+            let ecam = ecam(cmd, true).await?;
+            let profile = profile(cmd, &ecam).await?;
+            select_profile(ecam, profile).await?;
+        }
+        Some(("rename-profile", cmd)) => {
+            let name = cmd.get_one::<String>("name").expect("Required");
+            let icon = cmd
+                .get_one::<String>("icon")
+                .map(|s| s.parse::<u8>().expect("Invalid number"));
+            let ecam = ecam(cmd, true).await?;
+            let profile = profile(cmd, &ecam).await?;
+            let icon = if let Some(icon) = icon {
+                icon
+            } else {
+                read_profiles(ecam.clone())
+                    .await?
+                    .get(profile as usize - 1)
+                    .map(|p| p.icon())
+                    .unwrap_or_default()
+            };
+            rename_profile(ecam, profile, WideStringWithIcon::new(name, icon)).await?;
+        }
+        Some(("rename-recipe", cmd)) => {
+            let name = cmd.get_one::<String>("name").expect("Required");
+            let icon = cmd
+                .get_one::<String>("icon")
+                .map(|s| s.parse::<u8>().expect("Invalid number"));
+            let ecam = ecam(cmd, true).await?;
+            let beverage = beverage(cmd, &ecam).await?;
+            let icon = if let Some(icon) = icon {
+                icon
             } else {
-                let uuid = Uuid::parse_str(&device_name).expect("Failed to parse UUID");
-                let ecam = EcamBT::get(uuid).await?;
-                pipe_stdin(ecam).await?;
+                read_recipe_names(ecam.clone())
+                    .await?
+                    .iter()
+                    .find(|(id, _)| *id == beverage)
+                    .map(|(_, name)| name.icon())
+                    .unwrap_or_default()
+            };
+            rename_recipe(ecam, beverage, WideStringWithIcon::new(name, icon)).await?;
+        }
+        Some(("sync-time", cmd)) => {
+            let ecam = ecam(cmd, true).await?;
+            sync_time(ecam).await?;
+        }
+        Some(("beans", cmd)) => match cmd.subcommand() {
+            Some(("list", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                list_bean_systems(ecam).await?;
+            }
+            Some(("select", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                let beans = bean_system(cmd, &ecam).await?;
+                select_bean_system(ecam, beans).await?;
+            }
+            _ => unreachable!(),
+        },
+        Some(("pin", cmd)) => match cmd.subcommand() {
+            Some(("status", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                pin_status(ecam).await?;
             }
+            Some(("set", cmd)) => {
+                let pin = match Pin::parse(&read_secret("PIN: ")?) {
+                    Some(pin) => pin,
+                    None => {
+                        eprintln!("The PIN must be exactly four digits");
+                        return Ok(());
+                    }
+                };
+                let ecam = ecam(cmd, true).await?;
+                set_pin(ecam, pin).await?;
+            }
+            Some(("clear", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                clear_pin(ecam).await?;
+            }
+            _ => unreachable!(),
+        },
+        Some(("favorites", cmd)) => match cmd.subcommand() {
+            Some(("list", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                let profiles = if cmd.get_flag("all-profiles") {
+                    (1..=PROFILE_COUNT).collect()
+                } else if cmd.get_one::<String>("profile").is_some() {
+                    vec![profile(cmd, &ecam).await?]
+                } else {
+                    vec![]
+                };
+                list_favorites(ecam, &profiles).await?;
+            }
+            Some(("set", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                let profile = profile(cmd, &ecam).await?;
+                let mut beverages = vec![];
+                for beverage in cmd
+                    .get_one::<String>("beverages")
+                    .expect("Required")
+                    .split(',')
+                {
+                    beverages.push(resolve_beverage(ecam.clone(), beverage.trim()).await?);
+                }
+                if cmd.get_flag("select-profile") {
+                    select_profile(ecam.clone(), profile).await?;
+                }
+                set_favorites(ecam, profile, beverages).await?;
+            }
+            _ => unreachable!(),
+        },
+        Some(("statistics", cmd)) => {
+            let start = cmd
+                .get_one::<String>("start")
+                .map(|s| s.parse::<u16>().expect("Invalid number"))
+                .expect("Required");
+            let count = cmd
+                .get_one::<String>("count")
+                .map(|s| s.parse::<u16>().expect("Invalid number"))
+                .expect("Required");
+            let json = cmd.get_flag("json");
+            let ecam = ecam(cmd, true).await?;
+            list_statistics(ecam, start, count, json).await?;
+        }
+        Some(("serve", cmd)) => {
+            let addr = cmd
+                .get_one::<String>("bind")
+                .map(|s| s.parse::<SocketAddr>().expect("Invalid address"))
+                .expect("Required");
+            let ecam = ecam(cmd, true).await?;
+            app::web::serve(ecam, addr).await?;
+        }
+        Some(("bridge", cmd)) => {
+            let addr = cmd
+                .get_one::<String>("listen")
+                .map(|s| s.parse::<SocketAddr>().expect("Invalid address"))
+                .expect("Required");
+            bridge(addr, DeviceCommon::parse(cmd).builder()?).await?;
+        }
+        Some(("x-internal-pipe", cmd)) => {
+            let device_name = DeviceCommon::parse(cmd).device_name;
+            let driver = DriverSelection::parse(&device_name)?.connect().await?;
+            pipe_stdin(driver).await?;
         }
         _ => {}
     }
@@ -0,0 +1,105 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::{BeanSystem, MachineEnum, MachineEnumerable, Request, Response},
+};
+
+/// Reads the stored bean profiles, in order (ie: the first entry is bean profile 1). The machine doesn't report how
+/// many bean profiles it stores, so they are read one at a time until the first empty or invalid slot.
+pub async fn read_bean_systems(ecam: Ecam) -> Result<Vec<BeanSystem>, EcamError> {
+    let mut bean_systems = vec![];
+    for index in 1..=u8::MAX {
+        let beans = ecam
+            .request_matching(
+                Request::BeanSystemRead(index, index),
+                |response| match response {
+                    Response::BeanSystemRead(beans) => Some(beans),
+                    _ => None,
+                },
+            )
+            .await;
+        match beans {
+            Ok(beans) => match beans.into_iter().next() {
+                Some(beans) if !beans.name.is_empty() => bean_systems.push(beans),
+                _ => break,
+            },
+            Err(EcamError::Timeout(_) | EcamError::Decode(_)) if index > 1 => break,
+            Err(e) => return Err(e),
+        }
+    }
+    if bean_systems.is_empty() {
+        return Err(EcamError::NotFound);
+    }
+    Ok(bean_systems)
+}
+
+/// Resolves a bean profile given either as a number or as a bean profile name (case-insensitive). Numbers are passed
+/// through without reading the bean profiles, as the machine checks them itself.
+pub async fn resolve_bean_system(ecam: Ecam, beans: &str) -> Result<u8, EcamError> {
+    if let Ok(beans) = beans.parse::<u8>() {
+        if beans == 0 {
+            info!("Bean profiles are numbered from 1");
+            return Err(EcamError::InvalidArgument(
+                "bean profiles are numbered from 1".to_owned(),
+            ));
+        }
+        return Ok(beans);
+    }
+    let bean_systems = read_bean_systems(ecam).await?;
+    if let Some(i) = bean_systems
+        .iter()
+        .position(|b| b.name.eq_ignore_ascii_case(beans))
+    {
+        Ok(i as u8 + 1)
+    } else {
+        info!(
+            "No bean profile named '{}' (bean profiles are: {})",
+            beans,
+            bean_systems
+                .iter()
+                .map(|b| b.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Err(EcamError::NotFound)
+    }
+}
+
+fn enum_arg_string<T: MachineEnumerable<T>>(value: MachineEnum<T>) -> String {
+    match value {
+        MachineEnum::Value(value) => value.to_arg_string(),
+        MachineEnum::Unknown(value) => value.to_string(),
+    }
+}
+
+/// Lists the bean profiles stored in the device.
+pub async fn list_bean_systems(ecam: Ecam) -> Result<(), EcamError> {
+    // Wait for device to settle
+    ecam.wait_for_connection().await?;
+    let bean_systems = read_bean_systems(ecam).await?;
+    info!("Bean profiles:");
+    for (i, beans) in bean_systems.iter().enumerate() {
+        info!(
+            "  {}: {} (--temperature {} --taste {} --grind {})",
+            i + 1,
+            beans.name,
+            enum_arg_string(beans.temperature),
+            enum_arg_string(beans.taste),
+            beans.grind
+        );
+    }
+    Ok(())
+}
+
+/// Selects the given bean profile as the one the machine brews with.
+pub async fn select_bean_system(ecam: Ecam, beans: u8) -> Result<(), EcamError> {
+    ecam.request_matching(
+        Request::BeanSystemSelect(beans),
+        |response| match response {
+            Response::BeanSystemSelect() => Some(()),
+            _ => None,
+        },
+    )
+    .await?;
+    Ok(())
+}
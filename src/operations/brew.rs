@@ -2,54 +2,40 @@ use crate::{display, prelude::*};
 use crate::{
     ecam::{Ecam, EcamError, EcamStatus},
     operations::{
-        check_ingredients, list_recipies_for, BrewIngredientInfo, IngredientCheckError,
-        IngredientCheckMode,
+        check_ingredients, list_recipies_for_profile, BrewIngredientInfo, IngredientCheckMode,
+        DEFAULT_PROFILE,
     },
     protocol::*,
 };
 
-/// Checks the arguments for the given beverage against the machine's recipes and returns a computed recipe.
-pub async fn validate_brew(
+/// How long to wait for the machine to start brewing before assuming it refused the request.
+const BREW_START_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for the machine to finish brewing (or stopping) a beverage.
+const BREW_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Checks the arguments for the given beverage against the machine's recipe for the given profile, returning the
+/// complete set of ingredients to send to the machine.
+async fn validate_ingredients(
     ecam: Ecam,
+    profile: u8,
     beverage: EcamBeverageId,
     ingredients: Vec<BrewIngredientInfo>,
     mode: IngredientCheckMode,
-) -> Result<Vec<RecipeInfo<u16>>, EcamError> {
+) -> Result<Vec<BrewIngredientInfo>, EcamError> {
     info!("Fetching recipe for {:?}...", beverage);
-    let recipe_list = list_recipies_for(ecam.clone(), Some(vec![beverage])).await?;
+    let recipe_list =
+        list_recipies_for_profile(ecam.clone(), profile, Some(vec![beverage])).await?;
     let recipe = recipe_list.find(beverage);
     if let Some(recipe) = recipe {
         let ranges = recipe.fetch_ingredients();
         match check_ingredients(mode, &ingredients, &ranges) {
-            Err(IngredientCheckError {
-                missing,
-                extra,
-                range_errors,
-            }) => {
-                for m in missing {
-                    info!("{}", m.to_arg_string().unwrap_or(format!("{:?}", m)));
-                }
-                for e in extra {
-                    info!("{}", e.to_arg_string());
-                }
-                for r in range_errors {
-                    info!("{}", r.1);
+            Err(err) => {
+                for message in err.messages() {
+                    info!("{}", message);
                 }
-                Err(EcamError::Unknown)
-            }
-            Ok(result) => {
-                info!(
-                    "Brewing {:?} with {}...",
-                    beverage,
-                    result
-                        .iter()
-                        .collect_filter_map_join(" ", BrewIngredientInfo::to_arg_string)
-                );
-                Ok(result
-                    .iter()
-                    .map(BrewIngredientInfo::to_recipe_info)
-                    .collect())
+                Err(EcamError::IngredientCheck(err))
             }
+            Ok(result) => Ok(result),
         }
     } else {
         info!(
@@ -60,6 +46,111 @@ pub async fn validate_brew(
     }
 }
 
+/// Checks the arguments for the given beverage against the machine's recipes for [`DEFAULT_PROFILE`] and returns a
+/// computed recipe.
+pub async fn validate_brew(
+    ecam: Ecam,
+    beverage: EcamBeverageId,
+    ingredients: Vec<BrewIngredientInfo>,
+    mode: IngredientCheckMode,
+) -> Result<Vec<RecipeInfo<u16>>, EcamError> {
+    validate_brew_for_profile(ecam, DEFAULT_PROFILE, beverage, ingredients, mode).await
+}
+
+/// Checks the arguments for the given beverage against the machine's recipes for the given profile and returns a
+/// computed recipe.
+pub async fn validate_brew_for_profile(
+    ecam: Ecam,
+    profile: u8,
+    beverage: EcamBeverageId,
+    ingredients: Vec<BrewIngredientInfo>,
+    mode: IngredientCheckMode,
+) -> Result<Vec<RecipeInfo<u16>>, EcamError> {
+    let result = validate_ingredients(ecam, profile, beverage, ingredients, mode).await?;
+    info!(
+        "Brewing {:?} with {}...",
+        beverage,
+        result
+            .iter()
+            .collect_filter_map_join(" ", BrewIngredientInfo::to_arg_string)
+    );
+    Ok(result
+        .iter()
+        .map(BrewIngredientInfo::to_recipe_info)
+        .collect())
+}
+
+/// Saves the given ingredients as the recipe for a beverage in the given profile (including the `Custom01`..`Custom10`
+/// slots), then reads the recipe back to ensure that the device accepted it.
+///
+/// The machine saves recipes into its active profile, so `profile` must already be active (see
+/// [`select_profile`](crate::operations::select_profile)).
+/// Saving to any other profile is reported as [`EcamError::NotAccepted`] when the recipe is read back.
+pub async fn save_recipe(
+    ecam: Ecam,
+    profile: u8,
+    beverage: EcamBeverageId,
+    ingredients: Vec<BrewIngredientInfo>,
+    mode: IngredientCheckMode,
+) -> Result<(), EcamError> {
+    let result = validate_ingredients(ecam.clone(), profile, beverage, ingredients, mode).await?;
+    info!(
+        "Saving {:?} with {} to profile {}...",
+        beverage,
+        result
+            .iter()
+            .collect_filter_map_join(" ", BrewIngredientInfo::to_arg_string),
+        profile
+    );
+    let recipe: Vec<_> = result
+        .iter()
+        .map(BrewIngredientInfo::to_recipe_info)
+        .collect();
+
+    // Wait for the machine to acknowledge the save before reading the recipe back
+    ecam.request_matching(
+        Request::BeverageDispensingMode(
+            beverage.into(),
+            EcamOperationTrigger::Start.into(),
+            recipe.clone(),
+            EcamBeverageTasteType::Save.into(),
+        ),
+        |response| match response {
+            Response::BeverageDispensingMode(..) => Some(()),
+            _ => None,
+        },
+    )
+    .await?;
+
+    let saved = ecam
+        .request_matching(
+            Request::RecipeQuantityRead(profile, beverage.into()),
+            |response| match response {
+                Response::RecipeQuantityRead(p, b, ingredients)
+                    if p == profile && b == beverage =>
+                {
+                    Some(ingredients)
+                }
+                _ => None,
+            },
+        )
+        .await?;
+    let mismatched: Vec<_> = recipe.iter().filter(|r| !saved.contains(r)).collect();
+    if mismatched.is_empty() {
+        info!("Saved {:?} to profile {}", beverage, profile);
+        Ok(())
+    } else {
+        info!(
+            "Recipe for {:?} read back as {:?} after saving {:?} (is profile {} the active profile?)",
+            beverage, saved, mismatched, profile
+        );
+        Err(EcamError::NotAccepted(format!(
+            "recipe for {:?} read back as {:?}",
+            beverage, saved
+        )))
+    }
+}
+
 pub async fn brew(
     ecam: Ecam,
     skip_brew: bool,
@@ -80,20 +171,85 @@ pub async fn brew(
     }
 
     // Wait for not ready
-    ecam.wait_for_not_state(EcamStatus::Ready, display::display_status)
-        .await?;
+    ecam.wait_for_not_state_timeout(
+        EcamStatus::Ready,
+        display::display_status,
+        ecam.timeout_or(BREW_START_TIMEOUT),
+    )
+    .await?;
 
-    // Wait for not busy
-    ecam.wait_for(
+    // Wait for not busy. An alarm raised while brewing (ie: an empty water tank) ends the brew early.
+    ecam.wait_for_timeout(
         |m| match EcamStatus::extract(m) {
             EcamStatus::Busy(_) => false,
             _ => true,
         },
         display::display_status,
+        ecam.timeout_or(BREW_TIMEOUT),
     )
     .await?;
 
+    if let Some(EcamStatus::Alarm(alarm)) = ecam.last_status().await {
+        info!("Brew stopped by alarm {:?}", alarm);
+        return Err(EcamError::Alarm(alarm));
+    }
+
     display::log(display::LogLevel::Info, "Completed");
 
     Ok(())
 }
+
+/// Stops an in-progress brew of the given beverage and waits for the machine to return to the ready state.
+pub async fn stop_brew(ecam: Ecam, beverage: EcamBeverageId) -> Result<(), EcamError> {
+    let dispensed = match ecam.current_state().await? {
+        EcamStatus::Busy(percentage) => Some(percentage),
+        _ => None,
+    };
+    let req = Request::BeverageDispensingMode(
+        beverage.into(),
+        EcamOperationTrigger::Stop.into(),
+        vec![],
+        EcamBeverageTasteType::Prepare.into(),
+    );
+    ecam.write_request(req).await?;
+
+    // Wait for ready
+    ecam.wait_for_state_timeout(
+        EcamStatus::Ready,
+        display::display_status,
+        ecam.timeout_or(BREW_TIMEOUT),
+    )
+    .await?;
+
+    if let Some(dispensed) = dispensed {
+        display::log(
+            display::LogLevel::Info,
+            &format!("Stopped {:?} after dispensing {}%", beverage, dispensed),
+        );
+    } else {
+        display::log(display::LogLevel::Info, &format!("Stopped {:?}", beverage));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ecam::{DriverSelection, EcamBuilder};
+
+    #[tokio::test]
+    async fn brew_fails_with_alarm() -> Result<(), EcamError> {
+        let ecam = EcamBuilder::new(DriverSelection::parse("sim[on,alarm=emptywatertank@50]")?)
+            .connect()
+            .await?;
+        let recipe = vec![RecipeInfo::new(EcamIngredients::Coffee, 50)];
+        match brew(ecam, false, EcamBeverageId::RegularCoffee, recipe).await {
+            Err(EcamError::Alarm(alarm)) => {
+                assert_eq!(alarm, EcamMachineAlarm::EmptyWaterTank)
+            }
+            other => panic!("Expected an alarm, got {:?}", other),
+        }
+        Ok(())
+    }
+}
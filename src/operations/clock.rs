@@ -0,0 +1,19 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::{Request, Response},
+    util::local_time,
+};
+
+/// Sets the machine's clock to the host's local time, so that the machine's automatic power-on schedule fires at the
+/// expected time.
+pub async fn sync_time(ecam: Ecam) -> Result<(), EcamError> {
+    let (hour, minute) = local_time();
+    ecam.request_matching(Request::SetTime(hour, minute), |response| match response {
+        Response::SetTime() => Some(()),
+        _ => None,
+    })
+    .await?;
+    info!("Machine time set to {:02}:{:02}", hour, minute);
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    operations::{read_profiles, select_profile},
+    prelude::*,
+    protocol::*,
+};
+
+/// Reads the beverage ordering shown on the machine's home screen for the active profile.
+pub async fn read_favorites(ecam: Ecam) -> Result<Vec<MachineEnum<EcamBeverageId>>, EcamError> {
+    let priorities = ecam
+        .request_matching(Request::RecipePriorityRead(), |response| match response {
+            Response::RecipePriorityRead(priorities) => Some(priorities),
+            _ => None,
+        })
+        .await?;
+    Ok(priorities.into_iter().map(MachineEnum::decode).collect())
+}
+
+/// Lists the beverage ordering for the given profiles, or for the active profile if none are given. The machine only
+/// reports the ordering of the active profile, so each given profile is selected in turn and the last one stays active.
+pub async fn list_favorites(ecam: Ecam, profiles: &[u8]) -> Result<(), EcamError> {
+    // Wait for device to settle
+    ecam.wait_for_connection().await?;
+    if profiles.is_empty() {
+        info!("Active profile:");
+        print_favorites(&read_favorites(ecam).await?);
+        return Ok(());
+    }
+    let names = read_profiles(ecam.clone()).await.unwrap_or_default();
+    for profile in profiles {
+        select_profile(ecam.clone(), *profile).await?;
+        let favorites = read_favorites(ecam.clone()).await?;
+        match names.get(*profile as usize - 1) {
+            Some(name) => info!("Profile {} ({}):", profile, name.name()),
+            None => info!("Profile {}:", profile),
+        }
+        print_favorites(&favorites);
+    }
+    Ok(())
+}
+
+fn print_favorites(favorites: &[MachineEnum<EcamBeverageId>]) {
+    for (i, beverage) in favorites.iter().enumerate() {
+        match beverage {
+            MachineEnum::Value(beverage) => {
+                info!("  {:>2}: {}", i + 1, beverage.to_arg_string())
+            }
+            MachineEnum::Unknown(id) => info!("  {:>2}: unknown ({})", i + 1, id),
+        }
+    }
+}
+
+/// Sets the beverage ordering for the given profile, then reads it back to ensure that the device accepted it.
+///
+/// The machine only reports the ordering of the active profile, so `profile` must already be active (see
+/// [`select_profile`]) for the read-back to succeed.
+pub async fn set_favorites(
+    ecam: Ecam,
+    profile: u8,
+    beverages: Vec<EcamBeverageId>,
+) -> Result<(), EcamError> {
+    for (i, beverage) in beverages.iter().enumerate() {
+        if beverages[..i].contains(beverage) {
+            info!("{:?} is listed more than once", beverage);
+            return Err(EcamError::InvalidArgument(format!(
+                "{:?} is listed more than once",
+                beverage
+            )));
+        }
+    }
+
+    let beverages: Vec<MachineEnum<EcamBeverageId>> =
+        beverages.into_iter().map(MachineEnum::Value).collect();
+    ecam.request_matching(
+        Request::SetFavoriteBeverages(profile, beverages.clone()),
+        |response| match response {
+            Response::SetFavoriteBeverages() => Some(()),
+            _ => None,
+        },
+    )
+    .await?;
+
+    let actual = read_favorites(ecam).await?;
+    // The machine may append the beverages we didn't mention after the ones we did
+    if actual.starts_with(&beverages) {
+        Ok(())
+    } else {
+        info!(
+            "Favorites for profile {} read back as {:?} after setting them to {:?} (is profile {} the active profile?)",
+            profile, actual, beverages, profile
+        );
+        Err(EcamError::NotAccepted(format!(
+            "profile {} favorites",
+            profile
+        )))
+    }
+}
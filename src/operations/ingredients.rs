@@ -274,6 +274,25 @@ pub struct IngredientCheckError {
     pub range_errors: Vec<(EcamIngredients, String)>,
 }
 
+impl IngredientCheckError {
+    /// Describes each problem found, one message per missing, extra or out-of-range ingredient.
+    pub fn messages(&self) -> Vec<String> {
+        let missing = self
+            .missing
+            .iter()
+            .map(|m| m.to_arg_string().unwrap_or(format!("{:?}", m)));
+        let extra = self.extra.iter().map(|e| e.to_arg_string());
+        let range_errors = self.range_errors.iter().map(|r| r.1.clone());
+        missing.chain(extra).chain(range_errors).collect()
+    }
+}
+
+impl std::fmt::Display for IngredientCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.messages().join("; "))
+    }
+}
+
 /// Checks this [`BrewIngredientInfo`] against an [`IngredientRangeInfo`] and returns [`Ok(RecipeInfo)`] if valid.
 pub fn check_ingredients(
     mode: IngredientCheckMode,
@@ -479,4 +498,18 @@ mod test {
     ) {
         test_mode(IngredientCheckMode::AllowDefaults, ranges, input, expected);
     }
+
+    #[test]
+    fn error_messages() {
+        let err = check_ingredients(
+            IngredientCheckMode::Strict,
+            &quick_arg_parse("coffee 1000 milk 100"),
+            &ESPRESSO_RECIPE,
+        )
+        .expect_err("Expected an error");
+        assert_eq!(err.messages().len(), 2);
+        let e = crate::ecam::EcamError::IngredientCheck(err.clone());
+        assert_eq!(format!("invalid ingredients: {}", err), e.to_string());
+        assert!(matches!(e, crate::ecam::EcamError::IngredientCheck(inner) if inner == err));
+    }
 }
@@ -1,15 +1,29 @@
 //! Coffee-related operations: brewing, monitoring, etc.
 
+mod beans;
 mod brew;
+mod clock;
+mod favorites;
 mod ingredients;
 mod monitor;
 mod parameter;
+mod pin;
 mod power;
+mod profile;
 mod recipe_list;
+mod recipe_name;
+mod statistics;
 
+pub use beans::*;
 pub use brew::*;
+pub use clock::*;
+pub use favorites::*;
 pub use ingredients::*;
 pub use monitor::*;
 pub use parameter::*;
+pub use pin::*;
 pub use power::*;
+pub use profile::*;
 pub use recipe_list::*;
+pub use recipe_name::*;
+pub use statistics::*;
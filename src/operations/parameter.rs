@@ -1,34 +1,110 @@
 use crate::{
-    ecam::{Ecam, EcamError, EcamOutput},
+    ecam::{Ecam, EcamError},
     prelude::*,
-    protocol::Request,
+    protocol::{hexdump, Request, Response},
 };
 
+/// Parameters are read in blocks of this many bytes (see [`EcamRequestId::ParameterRead`](crate::protocol::EcamRequestId::ParameterRead)).
+const PARAMETER_BLOCK_SIZE: usize = 2;
+
+/// The most blocks that a single parameter read covers (see [`EcamRequestId::ParameterReadExt`](crate::protocol::EcamRequestId::ParameterReadExt)).
+const MAX_PARAMETER_BLOCKS: usize = 10;
+
+/// Reads a parameter from the device and displays its raw bytes.
 pub async fn read_parameter(ecam: Ecam, param: u16, len: u8) -> Result<(), EcamError> {
-    let mut tap = ecam.packet_tap().await?;
-    let ecam = ecam.clone();
-    let _handle = tokio::spawn(async move {
-        while let Some(packet) = tap.next().await {
-            // if dump_decoded_packets {
-            trace_packet!("{:?}", packet);
-            // }
-            if packet == EcamOutput::Done {
-                break;
+    let data = read_parameter_value(ecam, param, len).await?;
+    info!("Parameter {} = {}", param, hex::encode(&data));
+    Ok(())
+}
+
+/// Reads the raw bytes of a parameter from the device.
+pub async fn read_parameter_value(ecam: Ecam, param: u16, len: u8) -> Result<Vec<u8>, EcamError> {
+    ecam.request_matching(
+        parameter_read_request(param, len),
+        |response| match response {
+            Response::ParameterRead(p, data) | Response::ParameterReadExt(p, data)
+                if p == param =>
+            {
+                Some(data)
             }
-        }
-    });
+            _ => None,
+        },
+    )
+    .await
+}
 
-    if len > 4 {
-        ecam.write_request(Request::ParameterReadExt(param, len))
-            .await?;
+/// Writes the raw bytes of a parameter to the device, then reads the parameter back to ensure that the device
+/// accepted the new value.
+pub async fn write_parameter(ecam: Ecam, param: u16, data: Vec<u8>) -> Result<(), EcamError> {
+    let len = parameter_len(&data)?;
+    ecam.request_matching(
+        Request::ParameterWrite(param, data.clone()),
+        |response| match response {
+            Response::ParameterWrite(p, _) if p == param => Some(()),
+            _ => None,
+        },
+    )
+    .await?;
+
+    let value = read_parameter_value(ecam, param, len).await?;
+    if value == data {
+        Ok(())
     } else {
-        ecam.write_request(Request::ParameterRead(param, len))
-            .await?;
+        info!(
+            "Parameter {} read back as {} after writing {}",
+            param,
+            hexdump(&value),
+            hexdump(&data)
+        );
+        Err(EcamError::NotAccepted(format!("parameter {}", param)))
     }
+}
 
-    while ecam.is_alive() {
-        tokio::time::sleep(Duration::from_millis(100)).await;
+/// Determines the length in blocks to read back after writing `data`. Parameters must be written in whole
+/// blocks so that the read-back covers exactly the bytes written.
+fn parameter_len(data: &[u8]) -> Result<u8, EcamError> {
+    let (blocks, remainder) = (
+        data.len() / PARAMETER_BLOCK_SIZE,
+        data.len() % PARAMETER_BLOCK_SIZE,
+    );
+    if blocks == 0 || remainder != 0 {
+        return Err(EcamError::InvalidArgument(format!(
+            "parameters are written in blocks of {} bytes, but {} were provided",
+            PARAMETER_BLOCK_SIZE,
+            data.len()
+        )));
+    }
+    if blocks > MAX_PARAMETER_BLOCKS {
+        return Err(EcamError::InvalidArgument(format!(
+            "parameters are at most {} bytes, but {} were provided",
+            MAX_PARAMETER_BLOCKS * PARAMETER_BLOCK_SIZE,
+            data.len()
+        )));
     }
+    Ok(blocks as u8)
+}
 
-    Ok(())
+fn parameter_read_request(param: u16, len: u8) -> Request {
+    if len > 4 {
+        Request::ParameterReadExt(param, len)
+    } else {
+        Request::ParameterRead(param, len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(&[0, 3], Some(1))]
+    #[case(&[0, 0, 0, 3], Some(2))]
+    #[case(&[0; 20], Some(10))]
+    #[case(&[0, 0, 3], None)]
+    #[case(&[0; 22], None)]
+    #[case(&[], None)]
+    fn parameter_len_in_blocks(#[case] data: &[u8], #[case] expected: Option<u8>) {
+        assert_eq!(parameter_len(data).ok(), expected);
+    }
 }
@@ -0,0 +1,58 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::{Pin, PinLock, Request, Response},
+};
+
+/// Reads the state of the machine's PIN lock.
+pub async fn read_pin_lock(ecam: Ecam) -> Result<PinLock, EcamError> {
+    ecam.request_matching(Request::PinRead(), |response| match response {
+        Response::PinRead(lock) => Some(lock),
+        _ => None,
+    })
+    .await
+}
+
+/// Displays whether the machine's PIN lock is enabled. The PIN itself is never displayed.
+pub async fn pin_status(ecam: Ecam) -> Result<(), EcamError> {
+    // Wait for device to settle
+    ecam.wait_for_connection().await?;
+    if read_pin_lock(ecam).await?.enabled {
+        info!("PIN lock is enabled");
+    } else {
+        info!("PIN lock is disabled");
+    }
+    Ok(())
+}
+
+/// Writes the PIN lock state, then reads it back to ensure that the device accepted it.
+async fn write_pin_lock(ecam: Ecam, lock: PinLock) -> Result<(), EcamError> {
+    ecam.request_matching(Request::PinSet(lock), |response| match response {
+        Response::PinSet() => Some(()),
+        _ => None,
+    })
+    .await?;
+
+    let actual = read_pin_lock(ecam).await?;
+    // Only compare the PIN if the lock is enabled, as the device may not clear it when disabled
+    if actual.enabled == lock.enabled && (!lock.enabled || actual.pin == lock.pin) {
+        Ok(())
+    } else {
+        info!("The device did not accept the PIN change");
+        Err(EcamError::NotAccepted("PIN".to_owned()))
+    }
+}
+
+/// Enables the PIN lock with the given PIN.
+pub async fn set_pin(ecam: Ecam, pin: Pin) -> Result<(), EcamError> {
+    write_pin_lock(ecam, PinLock::enabled(pin)).await?;
+    info!("PIN lock enabled");
+    Ok(())
+}
+
+/// Disables the PIN lock.
+pub async fn clear_pin(ecam: Ecam) -> Result<(), EcamError> {
+    write_pin_lock(ecam, PinLock::disabled()).await?;
+    info!("PIN lock disabled");
+    Ok(())
+}
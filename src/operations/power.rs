@@ -3,6 +3,9 @@ use crate::ecam::{Ecam, EcamError, EcamStatus};
 use crate::prelude::*;
 use crate::protocol::*;
 
+/// How long to wait for the machine to turn on (including its rinse cycle) or off.
+const POWER_TIMEOUT: Duration = Duration::from_secs(180);
+
 pub async fn power_on(
     ecam: Ecam,
     allow_off: bool,
@@ -23,8 +26,12 @@ pub async fn power_on(
                 info!("Waiting for the machine to turn on...");
                 ecam.write_request(Request::AppControl(AppControl::TurnOn))
                     .await?;
-                ecam.wait_for_state(EcamStatus::Ready, display::display_status)
-                    .await?;
+                ecam.wait_for_state_timeout(
+                    EcamStatus::Ready,
+                    display::display_status,
+                    ecam.timeout_or(POWER_TIMEOUT),
+                )
+                .await?;
                 return Ok(true);
             }
         }
@@ -40,3 +47,29 @@ pub async fn power_on(
     }
     Ok(false)
 }
+
+/// Turns the machine off, waiting for it to finish shutting down and return to standby.
+pub async fn power_off(ecam: Ecam) -> Result<(), EcamError> {
+    match ecam.current_state().await? {
+        EcamStatus::StandBy => {
+            info!("Machine is already off");
+            return Ok(());
+        }
+        s @ EcamStatus::Busy(_) => {
+            info!("Machine is busy, so we will cowardly refuse to turn it off");
+            return Err(EcamError::WrongState(s));
+        }
+        _ => {}
+    }
+    info!("Waiting for the machine to turn off...");
+    ecam.write_request(Request::AppControl(AppControl::TurnOff))
+        .await?;
+    ecam.wait_for_state_timeout(
+        EcamStatus::StandBy,
+        display::display_status,
+        ecam.timeout_or(POWER_TIMEOUT),
+    )
+    .await?;
+    display::log(display::LogLevel::Info, "Machine is off");
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::{Request, Response, WideStringWithIcon},
+};
+
+/// The number of user profiles stored on the machine. The Dinamica Plus has three.
+pub const PROFILE_COUNT: u8 = 3;
+
+/// The profile used when none is specified.
+pub const DEFAULT_PROFILE: u8 = 1;
+
+/// Resolves a profile given either as a number or as a profile name (case-insensitive).
+pub async fn resolve_profile(ecam: Ecam, profile: &str) -> Result<u8, EcamError> {
+    if let Ok(profile) = profile.parse::<u8>() {
+        if (1..=PROFILE_COUNT).contains(&profile) {
+            return Ok(profile);
+        }
+        info!("Profile must be between 1 and {}", PROFILE_COUNT);
+        return Err(EcamError::NotFound);
+    }
+    let profiles = read_profiles(ecam).await?;
+    if let Some(i) = profiles
+        .iter()
+        .position(|p| p.name().eq_ignore_ascii_case(profile))
+    {
+        Ok(i as u8 + 1)
+    } else {
+        info!(
+            "No profile named '{}' (profiles are: {})",
+            profile,
+            profiles
+                .iter()
+                .map(|p| p.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Err(EcamError::NotFound)
+    }
+}
+
+/// Reads the names of the user profiles, in profile order (ie: the first name is profile 1).
+pub async fn read_profiles(ecam: Ecam) -> Result<Vec<WideStringWithIcon>, EcamError> {
+    ecam.request_matching(
+        Request::ProfileNameRead(1, PROFILE_COUNT),
+        |response| match response {
+            Response::ProfileNameRead(names) => Some(names),
+            _ => None,
+        },
+    )
+    .await
+}
+
+/// Lists the user profiles stored in the device.
+pub async fn list_profiles(ecam: Ecam) -> Result<(), EcamError> {
+    // Wait for device to settle
+    ecam.wait_for_connection().await?;
+    let profiles = read_profiles(ecam).await?;
+    info!("Profiles:");
+    for (i, profile) in profiles.iter().enumerate() {
+        info!("  {}: {} (icon {})", i + 1, profile.name(), profile.icon());
+    }
+    Ok(())
+}
+
+/// Selects the given profile as the machine's active profile.
+pub async fn select_profile(ecam: Ecam, profile: u8) -> Result<(), EcamError> {
+    ecam.request_matching(
+        Request::ProfileSelection(profile),
+        |response| match response {
+            Response::ProfileSelection() => Some(()),
+            _ => None,
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+/// Renames the given profile, then reads the profiles back to ensure that the device accepted the new name.
+pub async fn rename_profile(
+    ecam: Ecam,
+    profile: u8,
+    name: WideStringWithIcon,
+) -> Result<(), EcamError> {
+    if !name.is_valid() {
+        info!("Profile name '{}' is too long", name.name());
+        return Err(EcamError::InvalidArgument(format!(
+            "profile name '{}' is too long",
+            name.name()
+        )));
+    }
+
+    ecam.request_matching(
+        Request::ProfileNameWrite(profile, name.clone()),
+        |response| match response {
+            Response::ProfileNameWrite() => Some(()),
+            _ => None,
+        },
+    )
+    .await?;
+
+    let profiles = read_profiles(ecam).await?;
+    match profile
+        .checked_sub(1)
+        .and_then(|i| profiles.get(i as usize))
+    {
+        Some(actual) if *actual == name => Ok(()),
+        actual => {
+            info!(
+                "Profile {} read back as {:?} after renaming it to {:?}",
+                profile, actual, name
+            );
+            Err(EcamError::NotAccepted(format!("profile {} name", profile)))
+        }
+    }
+}
@@ -1,17 +1,18 @@
 use crate::{display, prelude::*};
 use crate::{
-    ecam::{Ecam, EcamError},
-    operations::IngredientRangeInfo,
+    ecam::{Ecam, EcamError, MAX_REQUEST_DURATION},
+    operations::{read_recipe_names, IngredientRangeInfo, DEFAULT_PROFILE},
     protocol::*,
 };
 use std::collections::HashMap;
 
 /// Accumulates recipe responses, allowing us to fetch them one-at-a-time and account for which ones went missing in transit.
-/// Note that this doesn't support profiles yet and currently requires the use of profile 1.
+/// Recipe quantities are fetched for [`DEFAULT_PROFILE`] unless another profile is chosen with [`RecipeAccumulator::for_profile`].
 pub struct RecipeAccumulator {
     recipe: HashMap<EcamBeverageId, Vec<RecipeInfo<u16>>>,
     recipe_min_max: HashMap<EcamBeverageId, Vec<RecipeMinMaxInfo>>,
     list: Vec<EcamBeverageId>,
+    profile: u8,
 }
 
 impl Default for RecipeAccumulator {
@@ -32,9 +33,16 @@ impl RecipeAccumulator {
             list: recipes,
             recipe: HashMap::new(),
             recipe_min_max: HashMap::new(),
+            profile: DEFAULT_PROFILE,
         }
     }
 
+    /// Fetches recipe quantities for the given profile rather than [`DEFAULT_PROFILE`].
+    pub fn for_profile(mut self, profile: u8) -> Self {
+        self.profile = profile;
+        self
+    }
+
     /// Lists the [`EcamBeverageId`]s which we still need to fetch information for.
     pub fn get_remaining_beverages(&self) -> Vec<EcamBeverageId> {
         let mut remaining = vec![];
@@ -54,7 +62,7 @@ impl RecipeAccumulator {
     pub fn get_request_packets(&self, beverage: EcamBeverageId) -> Vec<Request> {
         vec![
             Request::RecipeMinMaxSync(beverage.into()),
-            Request::RecipeQuantityRead(1, beverage.into()),
+            Request::RecipeQuantityRead(self.profile, beverage.into()),
         ]
     }
 
@@ -102,8 +110,8 @@ impl RecipeAccumulator {
     /// Accumulate a [`Response`] for the given [`EcamBeverageId`].
     pub fn accumulate_packet(&mut self, expected_beverage: EcamBeverageId, packet: Response) {
         match packet {
-            Response::RecipeQuantityRead(_, beverage, ingredients) => {
-                if beverage == expected_beverage {
+            Response::RecipeQuantityRead(profile, beverage, ingredients) => {
+                if beverage == expected_beverage && profile == self.profile {
                     self.recipe.insert(expected_beverage, ingredients);
                 }
             }
@@ -213,27 +221,64 @@ impl RecipeDetails {
     }
 }
 
-/// Lists recipes for either all recipes, or just the given ones.
+/// Lists recipes for either all recipes, or just the given ones, using the quantities stored for [`DEFAULT_PROFILE`].
 pub async fn list_recipies_for(
     ecam: Ecam,
     recipes: Option<Vec<EcamBeverageId>>,
 ) -> Result<RecipeList, EcamError> {
-    Ok(accumulate_recipies_for(ecam, recipes).await?.take())
+    list_recipies_for_profile(ecam, DEFAULT_PROFILE, recipes).await
+}
+
+/// Lists recipes for either all recipes, or just the given ones, using the quantities stored for the given profile.
+pub async fn list_recipies_for_profile(
+    ecam: Ecam,
+    profile: u8,
+    recipes: Option<Vec<EcamBeverageId>>,
+) -> Result<RecipeList, EcamError> {
+    Ok(accumulate_recipies_for_profile(ecam, profile, recipes)
+        .await?
+        .take())
 }
 
-/// Accumulates recipe min/max and ingredient info for either all recipes, or just the given ones.
+/// How long to spend fetching recipes before giving up, unless a single pass over the recipes could take longer (see
+/// [`recipe_fetch_timeout`]).
+const RECIPE_FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long to spend fetching recipes before giving up when `requests` requests are needed for a single pass. Each
+/// request may be retried, so the timeout allows for a pass in which every request needs all of its attempts.
+fn recipe_fetch_timeout(requests: usize) -> Duration {
+    RECIPE_FETCH_TIMEOUT.max(MAX_REQUEST_DURATION.saturating_mul(requests as u32))
+}
+
+/// Accumulates recipe min/max and ingredient info for either all recipes, or just the given ones, using the quantities
+/// stored for [`DEFAULT_PROFILE`].
 pub async fn accumulate_recipies_for(
     ecam: Ecam,
     recipes: Option<Vec<EcamBeverageId>>,
 ) -> Result<RecipeAccumulator, EcamError> {
-    // Get the tap we'll use for reading responses
-    let mut tap = ecam.packet_tap().await?;
+    accumulate_recipies_for_profile(ecam, DEFAULT_PROFILE, recipes).await
+}
+
+/// Accumulates recipe min/max and ingredient info for either all recipes, or just the given ones, using the quantities
+/// stored for the given profile.
+pub async fn accumulate_recipies_for_profile(
+    ecam: Ecam,
+    profile: u8,
+    recipes: Option<Vec<EcamBeverageId>>,
+) -> Result<RecipeAccumulator, EcamError> {
     let mut recipes = if let Some(recipes) = recipes {
         RecipeAccumulator::limited_to(recipes)
     } else {
         RecipeAccumulator::new()
-    };
+    }
+    .for_profile(profile);
     let total = recipes.get_remaining_beverages().len();
+    let requests = recipes
+        .get_remaining_beverages()
+        .into_iter()
+        .map(|beverage| recipes.get_request_packets(beverage).len())
+        .sum();
+    let deadline = std::time::Instant::now() + ecam.timeout_or(recipe_fetch_timeout(requests));
     for i in 0..3 {
         if i == 0 {
             info!("Fetching recipes...");
@@ -243,33 +288,35 @@ pub async fn accumulate_recipies_for(
                 recipes.get_remaining_beverages()
             );
         }
-        'outer: for beverage in recipes.get_remaining_beverages() {
-            'inner: for packet in recipes.get_request_packets(beverage) {
+        for beverage in recipes.get_remaining_beverages() {
+            for request in recipes.get_request_packets(beverage) {
                 crate::display::display_status(crate::ecam::EcamStatus::Fetching(
                     (total - recipes.get_remaining_beverages().len()) * 100 / total,
                 ));
-                let request_id = packet.ecam_request_id();
-                ecam.write_request(packet).await?;
-                let now = std::time::Instant::now();
-                while now.elapsed() < Duration::from_millis(500) {
-                    match tokio::time::timeout(Duration::from_millis(50), tap.next()).await {
-                        Err(_) => {}
-                        Ok(None) => {}
-                        Ok(Some(x)) => {
-                            if let Some(packet) = x.take_packet() {
-                                let response_id = packet.ecam_request_id();
-                                recipes.accumulate_packet(beverage, packet);
-                                // If this recipe is totally complete, move to the next one
-                                if recipes.is_complete(beverage) {
-                                    continue 'outer;
-                                }
-                                // If we got a response for the given request, move to the next packet/beverage
-                                if response_id == request_id {
-                                    continue 'inner;
-                                }
-                            }
+                if std::time::Instant::now() > deadline {
+                    info!("Timed out fetching recipes");
+                    return Err(EcamError::Timeout(ecam.last_status().await));
+                }
+                let response = ecam
+                    .request_matching(request, |response| match &response {
+                        Response::RecipeQuantityRead(p, b, _)
+                            if *p == profile && *b == beverage =>
+                        {
+                            Some(response)
                         }
-                    }
+                        Response::RecipeMinMaxSync(b, _) if *b == beverage => Some(response),
+                        _ => None,
+                    })
+                    .await;
+                match response {
+                    Ok(response) => recipes.accumulate_packet(beverage, response),
+                    // Anything that went missing is fetched again on the next pass
+                    Err(EcamError::Timeout(_)) => {}
+                    Err(e) => return Err(e),
+                }
+                // If this recipe is totally complete, move to the next one
+                if recipes.is_complete(beverage) {
+                    break;
                 }
             }
         }
@@ -280,17 +327,49 @@ pub async fn accumulate_recipies_for(
 }
 
 pub async fn list_recipes(ecam: Ecam) -> Result<(), EcamError> {
+    list_recipes_for_profile(ecam, DEFAULT_PROFILE).await
+}
+
+pub async fn list_recipes_for_profile(ecam: Ecam, profile: u8) -> Result<(), EcamError> {
     // Wait for device to settle
     ecam.wait_for_connection().await?;
-    let list = list_recipies_for(ecam, None).await?;
+    let list = list_recipies_for_profile(ecam.clone(), profile, None).await?;
+    let names = read_custom_recipe_names(ecam, &list).await;
     info!("Beverages supported:");
     for recipe in list.recipes {
-        info!("  {}", recipe.to_arg_string());
+        match names.iter().find(|(id, _)| *id == recipe.beverage) {
+            Some((_, name)) if !name.name().is_empty() => info!(
+                "  {}  # {} (icon {})",
+                recipe.to_arg_string(),
+                name.name(),
+                name.icon()
+            ),
+            _ => info!("  {}", recipe.to_arg_string()),
+        }
     }
 
     Ok(())
 }
 
+/// Reads the names of any custom recipes in the list, as custom recipes are easier to recognize by the names they
+/// were given on the machine. The names are best-effort: if they can't be read, an empty list is returned.
+pub async fn read_custom_recipe_names(
+    ecam: Ecam,
+    list: &RecipeList,
+) -> Vec<(EcamBeverageId, WideStringWithIcon)> {
+    if !list
+        .recipes
+        .iter()
+        .any(|r| r.beverage.custom_index().is_some())
+    {
+        return vec![];
+    }
+    read_recipe_names(ecam).await.unwrap_or_else(|_| {
+        warning!("Unable to read custom recipe names");
+        vec![]
+    })
+}
+
 fn enspacen(b: &[u8]) -> String {
     let mut s = "".to_owned();
     let space = "·";
@@ -314,12 +393,16 @@ fn enspacen(b: &[u8]) -> String {
 }
 
 pub async fn list_recipes_detailed(ecam: Ecam) -> Result<(), EcamError> {
+    list_recipes_detailed_for_profile(ecam, DEFAULT_PROFILE).await
+}
+
+pub async fn list_recipes_detailed_for_profile(ecam: Ecam, profile: u8) -> Result<(), EcamError> {
     use ariadne::{Color, Config, Label, Report, ReportBuilder, ReportKind, Source};
     const LINE_LIMIT: usize = 100;
 
     // Wait for device to settle
     ecam.wait_for_connection().await?;
-    let list = accumulate_recipies_for(ecam, None).await?;
+    let list = accumulate_recipies_for_profile(ecam, profile, None).await?;
     for beverage in EcamBeverageId::all() {
         let name = &format!("{:?}", beverage);
         let (recipe, minmax) = list.get(beverage);
@@ -405,9 +488,13 @@ pub async fn list_recipes_detailed(ecam: Ecam) -> Result<(), EcamError> {
 }
 
 pub async fn list_recipes_raw(ecam: Ecam) -> Result<(), EcamError> {
+    list_recipes_raw_for_profile(ecam, DEFAULT_PROFILE).await
+}
+
+pub async fn list_recipes_raw_for_profile(ecam: Ecam, profile: u8) -> Result<(), EcamError> {
     // Wait for device to settle
     ecam.wait_for_connection().await?;
-    let list = accumulate_recipies_for(ecam, None).await?;
+    let list = accumulate_recipies_for_profile(ecam, profile, None).await?;
     let mut s = "".to_owned();
 
     for beverage in EcamBeverageId::all() {
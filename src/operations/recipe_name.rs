@@ -0,0 +1,98 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::*,
+};
+
+/// Reads the names of the custom recipes, paired with the [`EcamBeverageId`] of their slot.
+pub async fn read_recipe_names(
+    ecam: Ecam,
+) -> Result<Vec<(EcamBeverageId, WideStringWithIcon)>, EcamError> {
+    let names = ecam
+        .request_matching(
+            Request::RecipeNameRead(1, CUSTOM_RECIPE_COUNT),
+            |response| match response {
+                Response::RecipeNameRead(names) => Some(names),
+                _ => None,
+            },
+        )
+        .await?;
+    Ok((1..=CUSTOM_RECIPE_COUNT)
+        .filter_map(EcamBeverageId::from_custom_index)
+        .zip(names)
+        .collect())
+}
+
+/// Resolves a beverage given either as a beverage name (ie: `regularcoffee` or `custom01`), or as the name of a custom
+/// recipe stored on the machine (case-insensitive).
+pub async fn resolve_beverage(ecam: Ecam, beverage: &str) -> Result<EcamBeverageId, EcamError> {
+    if let Some(beverage) = EcamBeverageId::lookup_by_name_case_insensitive(beverage) {
+        return Ok(beverage);
+    }
+    let names = read_recipe_names(ecam).await?;
+    if let Some((id, _)) = names
+        .iter()
+        .find(|(_, name)| name.name().eq_ignore_ascii_case(beverage))
+    {
+        Ok(*id)
+    } else {
+        info!(
+            "No beverage or custom recipe named '{}' (custom recipes are: {})",
+            beverage,
+            names
+                .iter()
+                .map(|(_, name)| name.name())
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Err(EcamError::NotFound)
+    }
+}
+
+/// Renames the given custom recipe, then reads the names back to ensure that the device accepted the new name.
+pub async fn rename_recipe(
+    ecam: Ecam,
+    beverage: EcamBeverageId,
+    name: WideStringWithIcon,
+) -> Result<(), EcamError> {
+    let index = if let Some(index) = beverage.custom_index() {
+        index
+    } else {
+        info!("Only custom recipes can be renamed, not {:?}", beverage);
+        return Err(EcamError::InvalidArgument(format!(
+            "{:?} is not a custom recipe",
+            beverage
+        )));
+    };
+    if !name.is_valid() {
+        info!("Recipe name '{}' is too long", name.name());
+        return Err(EcamError::InvalidArgument(format!(
+            "recipe name '{}' is too long",
+            name.name()
+        )));
+    }
+
+    ecam.request_matching(
+        Request::RecipeNameWrite(index, name.clone()),
+        |response| match response {
+            Response::RecipeNameWrite() => Some(()),
+            _ => None,
+        },
+    )
+    .await?;
+
+    let names = read_recipe_names(ecam).await?;
+    match names.iter().find(|(id, _)| *id == beverage) {
+        Some((_, actual)) if *actual == name => Ok(()),
+        actual => {
+            info!(
+                "{:?} read back as {:?} after renaming it to {:?}",
+                beverage,
+                actual.map(|(_, name)| name),
+                name
+            );
+            Err(EcamError::NotAccepted(format!("{:?} name", beverage)))
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use serde_json::{Map, Value};
+
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::{statistics_ranges, Request, Response},
+};
+
+/// Raw counter values read from the device, keyed by counter identifier. Decoding these into beverage counts, water
+/// volume and descale or filter counts is not supported until the identifiers are confirmed against a packet capture.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Statistics {
+    pub counters: Vec<(u16, u32)>,
+}
+
+impl Statistics {
+    /// Formats these counters as a JSON object keyed by counter identifier.
+    pub fn to_json(&self) -> Value {
+        Value::Object(Map::from_iter(
+            self.counters
+                .iter()
+                .map(|(id, value)| (id.to_string(), (*value).into())),
+        ))
+    }
+}
+
+/// Reads `count` counters starting at `start` from the device.
+pub async fn read_statistics(ecam: Ecam, start: u16, count: u16) -> Result<Statistics, EcamError> {
+    let mut counters = vec![];
+    for (start, count) in statistics_ranges(start, count) {
+        let values = ecam
+            .request_matching(
+                Request::StatisticsRead(start, count),
+                |response| match response {
+                    Response::StatisticsRead(id, values) if id == start => Some(values),
+                    _ => None,
+                },
+            )
+            .await?;
+        counters.extend((start..).zip(values.into_iter().take(count as usize)));
+    }
+    Ok(Statistics { counters })
+}
+
+/// Reads a range of raw counters from the device and displays them by identifier, either as a table or as JSON.
+pub async fn list_statistics(
+    ecam: Ecam,
+    start: u16,
+    count: u16,
+    json: bool,
+) -> Result<(), EcamError> {
+    // Wait for device to settle
+    ecam.wait_for_connection().await?;
+    let statistics = read_statistics(ecam, start, count).await?;
+    if json {
+        println!("{}", statistics.to_json());
+        return Ok(());
+    }
+
+    for (id, value) in statistics.counters.iter() {
+        info!("  {:<8}{:>10}", id, value);
+    }
+
+    Ok(())
+}
@@ -164,9 +164,17 @@ hardware_enum! {"Identifier determining the type of request and response (also r
     BeanSystemRead = 186,
     BeanSystemWrite = 187,
     PinRead = 210,
+    /// Set the machine's clock, used for the automatic power-on schedule.
     SetTime = 226,
 }}
 
+impl EcamRequestId {
+    /// Does this request or response carry the machine's PIN? These packets must never be traced.
+    pub fn is_sensitive(&self) -> bool {
+        matches!(self, EcamRequestId::PinSet | EcamRequestId::PinRead)
+    }
+}
+
 hardware_enum! {"The temperature of the dispensed beverage.", EcamTemperature {
     Low = 0,
     Mid = 1,
@@ -246,6 +254,32 @@ hardware_enum! {"The type of beverage to prepare.", EcamBeverageId {
     Custom10 = 239,
 }}
 
+/// The number of custom recipe slots, [`EcamBeverageId::Custom01`] through [`EcamBeverageId::Custom10`].
+pub const CUSTOM_RECIPE_COUNT: u8 = 10;
+
+impl EcamBeverageId {
+    /// The one-based index of this beverage's slot if it is a custom recipe, as used by
+    /// [`Request::RecipeNameRead`](super::Request::RecipeNameRead) and
+    /// [`Request::RecipeNameWrite`](super::Request::RecipeNameWrite).
+    pub fn custom_index(&self) -> Option<u8> {
+        let index = (*self as u8).checked_sub(EcamBeverageId::Custom01 as u8)? + 1;
+        if index <= CUSTOM_RECIPE_COUNT {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the custom recipe for the given one-based slot index.
+    pub fn from_custom_index(index: u8) -> Option<EcamBeverageId> {
+        if (1..=CUSTOM_RECIPE_COUNT).contains(&index) {
+            EcamBeverageId::try_from(EcamBeverageId::Custom01 as u8 + index - 1).ok()
+        } else {
+            None
+        }
+    }
+}
+
 hardware_enum! {"The set of alarms the machine can produce.", EcamMachineAlarm {
     EmptyWaterTank = 0,
     CoffeeWasteContainerFull = 1,
@@ -4,11 +4,13 @@ mod hardware_enums;
 mod machine_enum;
 mod packet;
 mod request;
+mod statistics;
 
 pub use hardware_enums::*;
 pub use machine_enum::*;
 pub use packet::*;
 pub use request::*;
+pub use statistics::*;
 
 #[cfg(test)]
 pub mod test {
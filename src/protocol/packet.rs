@@ -1,4 +1,5 @@
 use crate::protocol::request::{PartialDecode, PartialEncode};
+use crate::protocol::EcamRequestId;
 use crc::{Algorithm, Crc};
 use std::fmt::Debug;
 
@@ -10,7 +11,7 @@ pub struct EcamDriverPacket {
 
 impl Debug for EcamDriverPacket {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&hexdump(&self.bytes))
+        f.write_str(&trace_hexdump(&self.bytes))
     }
 }
 
@@ -146,9 +147,23 @@ pub fn hexdump(buffer: &[u8]) -> String {
     format!("|{}| |{}|", s1, s2)
 }
 
+/// Dumps a packet to a readable hex form like [`hexdump`], but hides the contents of packets that carry the machine's
+/// PIN. The buffer may be either a complete packet with sync byte and length, or just the packet contents.
+pub fn trace_hexdump(buffer: &[u8]) -> String {
+    let id = match buffer {
+        [0x0d | 0xd0, _, id, ..] => *id,
+        [id, ..] => *id,
+        [] => return hexdump(buffer),
+    };
+    match EcamRequestId::try_from(id) {
+        Ok(id) if id.is_sensitive() => format!("<{:?} packet redacted>", id),
+        _ => hexdump(buffer),
+    }
+}
+
 #[cfg(test)]
 pub mod test {
-    use super::{checksum, packetize};
+    use super::{checksum, packetize, trace_hexdump};
 
     pub fn from_hex_str(s: &str) -> Vec<u8> {
         hex::decode(s.replace(' ', "")).unwrap()
@@ -186,4 +201,17 @@ pub mod test {
             from_hex_str("0d 05 75 f0 c4 d5")
         );
     }
+
+    #[test]
+    pub fn test_trace_hexdump_redacts_pin() {
+        for packet in [
+            from_hex_str("b1 f0 01 04 d2"),
+            packetize(&from_hex_str("b1 f0 01 04 d2")),
+            from_hex_str("d0 08 d2 f0 01 04 d2 00 00"),
+        ] {
+            assert!(!trace_hexdump(&packet).contains("d2 "));
+            assert!(!trace_hexdump(&packet).contains("04d2"));
+        }
+        assert!(trace_hexdump(&from_hex_str("75 f0")).contains("75f0"));
+    }
 }
@@ -7,6 +7,8 @@ use super::PartialEncode;
 pub enum AppControl {
     /// Turns the machine on.
     TurnOn,
+    /// Turns the machine off, putting it back into standby (after rinsing, if enabled).
+    TurnOff,
     /// Uncertain, but sent by the application.
     RefreshAppId,
 }
@@ -15,6 +17,7 @@ impl PartialEncode for AppControl {
     fn partial_encode(&self, out: &mut Vec<u8>) {
         match self {
             Self::TurnOn => out.extend_from_slice(&[2, 1]),
+            Self::TurnOff => out.extend_from_slice(&[1, 1]),
             Self::RefreshAppId => out.extend_from_slice(&[3, 2]),
         }
     }
@@ -0,0 +1,71 @@
+use super::profile::decode_wide_name;
+use super::PartialDecode;
+use crate::protocol::{EcamBeverageTaste, EcamTemperature, MachineEnum};
+
+/// A stored bean profile: the name of a bean type along with the settings the machine uses when grinding and brewing
+/// it.
+///
+/// This layout (a name of ten wide characters followed by temperature, taste and grind bytes) follows the profile name
+/// packets and has not been confirmed against a packet capture, so bean profiles can be read but not written.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BeanSystem {
+    pub name: String,
+    pub temperature: MachineEnum<EcamTemperature>,
+    pub taste: MachineEnum<EcamBeverageTaste>,
+    /// The grinder setting, where lower values are finer.
+    pub grind: u8,
+}
+
+impl BeanSystem {
+    pub fn new(
+        name: &str,
+        temperature: EcamTemperature,
+        taste: EcamBeverageTaste,
+        grind: u8,
+    ) -> Self {
+        BeanSystem {
+            name: name.to_owned(),
+            temperature: temperature.into(),
+            taste: taste.into(),
+            grind,
+        }
+    }
+}
+
+impl PartialDecode<BeanSystem> for BeanSystem {
+    fn partial_decode(input: &mut &[u8]) -> Option<BeanSystem> {
+        Some(BeanSystem {
+            name: decode_wide_name(input)?,
+            temperature: <MachineEnum<EcamTemperature>>::partial_decode(input)?,
+            taste: <MachineEnum<EcamBeverageTaste>>::partial_decode(input)?,
+            grind: <u8>::partial_decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_bean_system() {
+        let mut buf = "Arabica"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect::<Vec<_>>();
+        buf.resize(20, 0);
+        buf.extend_from_slice(&[2, 4, 5]);
+        assert_eq!(
+            BeanSystem::decode(&buf),
+            (
+                Some(BeanSystem::new(
+                    "Arabica",
+                    EcamTemperature::High,
+                    EcamBeverageTaste::Strong,
+                    5
+                )),
+                &[][..]
+            )
+        );
+    }
+}
@@ -1,11 +1,15 @@
 mod app_control;
+mod bean_system;
 mod monitor;
+mod pin;
 mod profile;
 mod recipe;
 
 use super::{hardware_enums::*, machine_enum::*};
 pub use app_control::*;
+pub use bean_system::*;
 pub use monitor::*;
+pub use pin::*;
 pub use profile::*;
 pub use recipe::*;
 
@@ -33,6 +37,12 @@ impl PartialEncode for u16 {
     }
 }
 
+impl PartialEncode for u32 {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
 impl<T: PartialEncode> PartialEncode for Vec<T> {
     fn partial_encode(&self, out: &mut Vec<u8>) {
         for t in self.iter() {
@@ -41,9 +51,9 @@ impl<T: PartialEncode> PartialEncode for Vec<T> {
     }
 }
 
-impl<T: MachineEnumerable<T>> PartialEncode for &MachineEnum<T> {
+impl<T: MachineEnumerable<T>> PartialEncode for MachineEnum<T> {
     fn partial_encode(&self, out: &mut Vec<u8>) {
-        out.push((**self).into())
+        out.push((*self).into())
     }
 }
 
@@ -93,6 +103,14 @@ impl PartialDecode<u16> for u16 {
     }
 }
 
+impl PartialDecode<u32> for u32 {
+    fn partial_decode(input: &mut &[u8]) -> Option<u32> {
+        let a = <u16>::partial_decode(input)? as u32;
+        let b = <u16>::partial_decode(input)? as u32;
+        Some((a << 16) | b)
+    }
+}
+
 macro_rules! packet_definition {
     (
         $(
@@ -197,27 +215,27 @@ packet_definition!(
         ingredients Vec<RecipeInfo<u16>>,
         mode MachineEnum<EcamBeverageTasteType>) => (unknown0 u8, unknown1 u8),
     AppControl(request AppControl) => (),
-    ParameterRead(parameter u16, len u8) => (),
-    ParameterWrite() => (),
-    ParameterReadExt(parameter u16, len u8) => (),
-    StatisticsRead(parameter u16, len u8) => (),
+    ParameterRead(parameter u16, len u8) => (parameter u16, data Vec<u8>),
+    ParameterWrite(parameter u16, data Vec<u8>) => (parameter u16, data Vec<u8>),
+    ParameterReadExt(parameter u16, len u8) => (parameter u16, data Vec<u8>),
+    StatisticsRead(parameter u16, len u8) => (parameter u16, values Vec<u32>),
     Checksum() => (),
     ProfileNameRead(start u8, end u8) => (names Vec<WideStringWithIcon>),
-    ProfileNameWrite() => (),
+    ProfileNameWrite(profile u8, name WideStringWithIcon) => (),
     RecipeQuantityRead(profile u8, recipe MachineEnum<EcamBeverageId>)
         => (profile u8, recipe MachineEnum<EcamBeverageId>, ingredients Vec<RecipeInfo<u16>>),
     RecipePriorityRead() => (priorities Vec<u8>),
-    ProfileSelection() => (),
+    ProfileSelection(profile u8) => (),
     RecipeNameRead(start u8, end u8) => (names Vec<WideStringWithIcon>),
-    RecipeNameWrite() => (),
-    SetFavoriteBeverages(profile u8, recipies Vec<u8>) => (),
+    RecipeNameWrite(recipe u8, name WideStringWithIcon) => (),
+    SetFavoriteBeverages(profile u8, recipies Vec<MachineEnum<EcamBeverageId>>) => (),
     RecipeMinMaxSync(recipe MachineEnum<EcamBeverageId>) => (recipe MachineEnum<EcamBeverageId>, bounds Vec<RecipeMinMaxInfo>),
-    PinSet() => (),
-    BeanSystemSelect() => (),
-    BeanSystemRead() => (),
+    PinSet(lock PinLock) => (),
+    BeanSystemSelect(bean u8) => (),
+    BeanSystemRead(start u8, end u8) => (beans Vec<BeanSystem>),
     BeanSystemWrite() => (),
-    PinRead() => (),
-    SetTime() => (),
+    PinRead() => (lock PinLock),
+    SetTime(hour u8, minute u8) => (),
 );
 
 impl Request {
@@ -250,7 +268,7 @@ mod test {
     fn real_packets_decode_as_expected(#[case] bytes: &[u8]) {
         let (packet, remainder) = Response::decode(unwrap_packet(bytes));
         let packet = packet.expect("Expected to decode something");
-        assert_eq!(remainder, &[]);
+        assert_eq!(remainder, &[] as &[u8]);
         // Not actually testing the decoding of these packets, but at least we can print it
         println!("{:?}", packet);
     }
@@ -316,6 +334,97 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_parameter_write() {
+        assert_eq!(
+            Request::ParameterWrite(0x00fd, vec![0x00, 0x00, 0x00, 0x03]).encode(),
+            vec![0x90, 0xf0, 0x00, 0xfd, 0x00, 0x00, 0x00, 0x03]
+        );
+    }
+
+    #[test]
+    fn test_decode_parameter_read() {
+        let buf = [149_u8, 240, 0, 253, 0, 0, 0, 3];
+        let input = &mut buf.as_slice();
+        assert_eq!(
+            <Response>::partial_decode(input).expect("Failed to decode"),
+            Response::ParameterRead(0x00fd, vec![0, 0, 0, 3])
+        );
+    }
+
+    #[test]
+    fn test_decode_statistics_read() {
+        let buf = [162_u8, 240, 3, 234, 0, 0, 1, 2, 0, 0, 0, 3];
+        let input = &mut buf.as_slice();
+        assert_eq!(
+            <Response>::partial_decode(input).expect("Failed to decode"),
+            Response::StatisticsRead(1002, vec![258, 3])
+        );
+    }
+
+    #[test]
+    fn test_profile_name_write() {
+        let mut expected = vec![165_u8, 240, 2, 0, 77, 0, 105, 0, 97];
+        expected.extend_from_slice(&[0; 14]);
+        expected.push(8);
+        assert_eq!(
+            Request::ProfileNameWrite(2, WideStringWithIcon::new("Mia", 8)).encode(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_recipe_name_write() {
+        let mut expected = vec![171_u8, 240, 1, 0, 76, 0, 97, 0, 116, 0, 116, 0, 101];
+        expected.extend_from_slice(&[0; 10]);
+        expected.push(3);
+        assert_eq!(
+            Request::RecipeNameWrite(1, WideStringWithIcon::new("Latte", 3)).encode(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_decode_bean_system_read() {
+        let buf = [
+            186_u8, 240, 0, 65, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 0,
+            66, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 4, 7,
+        ];
+        let input = &mut buf.as_slice();
+        assert_eq!(
+            <Response>::partial_decode(input).expect("Failed to decode"),
+            Response::BeanSystemRead(vec![
+                BeanSystem::new("A", EcamTemperature::Low, EcamBeverageTaste::Mild, 3),
+                BeanSystem::new("B", EcamTemperature::High, EcamBeverageTaste::Strong, 7),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_set_favorite_beverages() {
+        assert_eq!(
+            Request::SetFavoriteBeverages(
+                2,
+                vec![
+                    EcamBeverageId::Cappuccino.into(),
+                    EcamBeverageId::Custom01.into()
+                ]
+            )
+            .encode(),
+            vec![173, 240, 2, 7, 230]
+        );
+    }
+
+    #[test]
+    fn test_decode_recipe_priority_read() {
+        let buf = [168_u8, 240, 2, 7, 230];
+        let input = &mut buf.as_slice();
+        assert_eq!(
+            <Response>::partial_decode(input).expect("Failed to decode"),
+            Response::RecipePriorityRead(vec![2, 7, 230])
+        );
+    }
+
     #[test]
     fn test_brew_coffee() {
         let recipe = vec![
@@ -334,4 +443,35 @@ mod test {
             vec![0x83, 0xf0, 0x02, 0x01, 0x01, 0x00, 0x67, 0x02, 0x02, 0x00, 0x00, 0x06]
         );
     }
+
+    #[test]
+    fn test_app_control() {
+        assert_eq!(
+            Request::AppControl(AppControl::TurnOn).encode(),
+            vec![0x84, 0x0f, 0x02, 0x01]
+        );
+        assert_eq!(
+            Request::AppControl(AppControl::TurnOff).encode(),
+            vec![0x84, 0x0f, 0x01, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_set_time() {
+        assert_eq!(Request::SetTime(7, 45).encode(), vec![0xe2, 0xf0, 7, 45]);
+    }
+
+    #[test]
+    fn test_stop_coffee() {
+        assert_eq!(
+            Request::BeverageDispensingMode(
+                EcamBeverageId::RegularCoffee.into(),
+                EcamOperationTrigger::Stop.into(),
+                vec![],
+                EcamBeverageTasteType::Prepare.into()
+            )
+            .encode(),
+            vec![0x83, 0xf0, 0x02, 0x04, 0x02]
+        );
+    }
 }
@@ -30,7 +30,9 @@ impl<T: MachineEnumerable<T>> PartialDecode<SwitchSet<T>> for SwitchSet<T> {
 
 impl<T: MachineEnumerable<T>> PartialEncode for SwitchSet<T> {
     fn partial_encode(&self, out: &mut Vec<u8>) {
-        self.value.partial_encode(out)
+        // Mirrors the byte order of the decoder above
+        out.push(self.value as u8);
+        out.push((self.value >> 8) as u8);
     }
 }
 
@@ -70,7 +72,7 @@ impl PartialEncode for MonitorV2Response {
 
 #[cfg(test)]
 mod test {
-    use crate::protocol::EcamMachineSwitch;
+    use crate::protocol::{EcamMachineAlarm, EcamMachineSwitch, PartialDecode, PartialEncode};
 
     use super::SwitchSet;
 
@@ -82,4 +84,16 @@ mod test {
             SwitchSet::of(&[EcamMachineSwitch::MotorDown, EcamMachineSwitch::WaterSpout]);
         assert_eq!("WaterSpout | MotorDown", format!("{:?}", switches));
     }
+
+    #[test]
+    fn switch_set_round_trip() {
+        let alarms = SwitchSet::of(&[
+            EcamMachineAlarm::CoffeeWasteContainerFull,
+            EcamMachineAlarm::CleanKnob,
+        ]);
+        let mut out = vec![];
+        alarms.partial_encode(&mut out);
+        let decoded = <SwitchSet<EcamMachineAlarm>>::partial_decode(&mut &out[..]).unwrap();
+        assert_eq!(format!("{:?}", alarms), format!("{:?}", decoded));
+    }
 }
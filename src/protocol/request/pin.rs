@@ -0,0 +1,91 @@
+use super::{PartialDecode, PartialEncode};
+
+/// A four-digit machine PIN. The [`Debug`] representation never includes the digits, so a PIN can't leak into logs or
+/// packet traces.
+#[derive(Clone, Copy, Default, Eq, PartialEq)]
+pub struct Pin(u16);
+
+impl Pin {
+    /// Parses a PIN from a string of exactly four digits.
+    pub fn parse(s: &str) -> Option<Pin> {
+        if s.len() == 4 && s.chars().all(|c| c.is_ascii_digit()) {
+            s.parse().ok().map(Pin)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Debug for Pin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Pin(****)")
+    }
+}
+
+impl PartialDecode<Pin> for Pin {
+    fn partial_decode(input: &mut &[u8]) -> Option<Pin> {
+        Some(Pin(<u16>::partial_decode(input)?))
+    }
+}
+
+impl PartialEncode for Pin {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        self.0.partial_encode(out)
+    }
+}
+
+/// The state of the machine's PIN lock.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PinLock {
+    pub enabled: bool,
+    pub pin: Pin,
+}
+
+impl PinLock {
+    /// A lock that requires the given PIN.
+    pub fn enabled(pin: Pin) -> Self {
+        PinLock { enabled: true, pin }
+    }
+
+    /// A lock that is turned off.
+    pub fn disabled() -> Self {
+        PinLock::default()
+    }
+}
+
+impl PartialDecode<PinLock> for PinLock {
+    fn partial_decode(input: &mut &[u8]) -> Option<PinLock> {
+        Some(PinLock {
+            enabled: <u8>::partial_decode(input)? != 0,
+            pin: <Pin>::partial_decode(input)?,
+        })
+    }
+}
+
+impl PartialEncode for PinLock {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        out.push(self.enabled as u8);
+        self.pin.partial_encode(out);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pin_parse() {
+        assert_eq!(Pin::parse("0420"), Some(Pin(420)));
+        assert_eq!(Pin::parse("420"), None);
+        assert_eq!(Pin::parse("04200"), None);
+        assert_eq!(Pin::parse("04a0"), None);
+        assert_eq!(Pin::parse("+420"), None);
+    }
+
+    #[test]
+    fn pin_never_debug_printed() {
+        let lock = PinLock::enabled(Pin::parse("1234").unwrap());
+        assert!(!format!("{:?}", lock).contains("1234"));
+        assert_eq!(lock.encode(), vec![1, 0x04, 0xd2]);
+    }
+}
@@ -1,4 +1,7 @@
-use super::PartialDecode;
+use super::{PartialDecode, PartialEncode};
+
+/// The number of wide characters stored for a name.
+const NAME_LENGTH: usize = 10;
 
 /// Represents a recipe or profile name with an associate icon tucked into the last byte.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -8,31 +11,117 @@ pub struct WideStringWithIcon {
 }
 
 impl WideStringWithIcon {
-    #[cfg(test)]
+    /// Creates a new name. The machine only stores ten characters, so longer names are truncated when encoded.
     pub fn new(name: &str, icon: u8) -> Self {
         WideStringWithIcon {
             name: name.to_owned(),
             icon,
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn icon(&self) -> u8 {
+        self.icon
+    }
+
+    /// Returns true if this name can be stored on the machine without being truncated.
+    pub fn is_valid(&self) -> bool {
+        is_valid_wide_name(&self.name)
+    }
+}
+
+/// Decodes a fixed-length, zero-padded name of [`NAME_LENGTH`] wide characters, returning `None` if the input is too
+/// short or contains a character that isn't valid on its own (ie: half of a surrogate pair).
+pub(super) fn decode_wide_name(input: &mut &[u8]) -> Option<String> {
+    let mut s = vec![];
+    for _ in 0..NAME_LENGTH {
+        let b1 = <u8>::partial_decode(input)? as u16;
+        let b2 = <u8>::partial_decode(input)? as u16;
+        let char = char::from_u32(((b1 << 8) | b2) as u32)?;
+        s.push(char);
+    }
+    Some(
+        s.iter()
+            .collect::<String>()
+            .trim_end_matches(&['\0'])
+            .to_owned(),
+    )
+}
+
+/// Encodes a name as [`NAME_LENGTH`] wide characters, truncating or zero-padding it as needed. Characters outside of
+/// the basic multilingual plane can't be stored in a single wide character, so they are replaced.
+pub(super) fn encode_wide_name(name: &str, out: &mut Vec<u8>) {
+    let mut chars = name
+        .chars()
+        .take(NAME_LENGTH)
+        .map(|c| u16::try_from(c as u32).unwrap_or(char::REPLACEMENT_CHARACTER as u16))
+        .collect::<Vec<_>>();
+    chars.resize(NAME_LENGTH, 0);
+    chars.partial_encode(out);
+}
+
+/// Returns true if this name can be stored on the machine without being truncated or altered. Each character is stored
+/// as a single wide character, so characters outside of the basic multilingual plane are rejected.
+pub(super) fn is_valid_wide_name(name: &str) -> bool {
+    name.chars().count() <= NAME_LENGTH && name.chars().all(|c| (c as u32) <= 0xffff)
 }
 
 impl PartialDecode<WideStringWithIcon> for WideStringWithIcon {
     fn partial_decode(input: &mut &[u8]) -> Option<WideStringWithIcon> {
-        let mut s = vec![];
-        for _ in 0..10 {
-            let b1 = <u8>::partial_decode(input)? as u16;
-            let b2 = <u8>::partial_decode(input)? as u16;
-            let char = char::from_u32(((b1 << 8) | b2) as u32).expect("Invalid character");
-            s.push(char);
-        }
         Some(WideStringWithIcon {
-            name: s
-                .iter()
-                .collect::<String>()
-                .trim_end_matches(&['\0'])
-                .to_owned(),
+            name: decode_wide_name(input)?,
             icon: <u8>::partial_decode(input)?,
         })
     }
 }
+
+impl PartialEncode for WideStringWithIcon {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        encode_wide_name(&self.name, out);
+        out.push(self.icon);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wide_string_round_trip() {
+        let name = WideStringWithIcon::new("Matt", 3);
+        let encoded = name.encode();
+        assert_eq!(encoded.len(), NAME_LENGTH * 2 + 1);
+        assert_eq!(&encoded[..4], &[0, 77, 0, 97]);
+        assert_eq!(WideStringWithIcon::decode(&encoded), (Some(name), &[][..]));
+    }
+
+    #[test]
+    fn wide_string_truncated() {
+        let name = WideStringWithIcon::new("A very long name", 1);
+        assert!(!name.is_valid());
+        assert_eq!(
+            WideStringWithIcon::decode(&name.encode()).0,
+            Some(WideStringWithIcon::new("A very lon", 1))
+        );
+    }
+
+    #[test]
+    fn wide_string_outside_bmp() {
+        let name = WideStringWithIcon::new("Tea \u{1f375}", 1);
+        assert!(!name.is_valid());
+        assert_eq!(
+            WideStringWithIcon::decode(&name.encode()).0,
+            Some(WideStringWithIcon::new("Tea \u{fffd}", 1))
+        );
+    }
+
+    #[test]
+    fn wide_string_lone_surrogate() {
+        let mut encoded = vec![0xd8, 0x3c];
+        encoded.resize(NAME_LENGTH * 2 + 1, 0);
+        assert_eq!(WideStringWithIcon::decode(&encoded).0, None);
+    }
+}
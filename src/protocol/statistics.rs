@@ -0,0 +1,34 @@
+//! Usage counters, readable with [`Request::StatisticsRead`](super::Request::StatisticsRead).
+//!
+//! Counters are four bytes wide and are laid out in ranges of consecutive identifiers, so a single request can read
+//! several of them at once. The meaning of each identifier has not yet been confirmed against a packet capture, so
+//! counters are addressed by their raw identifiers.
+
+/// The maximum number of counters we request at once.
+pub const MAX_STATISTICS_PER_READ: u16 = 10;
+
+/// Splits the `count` counters starting at `start` into `(start, count)` requests, each no longer than
+/// [`MAX_STATISTICS_PER_READ`].
+pub fn statistics_ranges(start: u16, count: u16) -> Vec<(u16, u8)> {
+    let end = start.saturating_add(count);
+    (start..end)
+        .step_by(MAX_STATISTICS_PER_READ as usize)
+        .map(|id| (id, (end - id).min(MAX_STATISTICS_PER_READ) as u8))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(0, 0, vec![])]
+    #[case(1000, 3, vec![(1000, 3)])]
+    #[case(1000, 10, vec![(1000, 10)])]
+    #[case(1000, 23, vec![(1000, 10), (1010, 10), (1020, 3)])]
+    #[case(u16::MAX - 1, 5, vec![(u16::MAX - 1, 1)])]
+    fn statistic_ranges(#[case] start: u16, #[case] count: u16, #[case] expected: Vec<(u16, u8)>) {
+        assert_eq!(statistics_ranges(start, count), expected);
+    }
+}
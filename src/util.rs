@@ -17,3 +17,10 @@ impl<T: Iterator<Item = X>, X> CollectMapJoin<X> for T {
         self.filter_map(f).collect::<Vec<String>>().join(sep)
     }
 }
+
+/// Returns the host's local time of day as `(hour, minute)`.
+pub fn local_time() -> (u8, u8) {
+    use chrono::Timelike;
+    let now = chrono::Local::now();
+    (now.hour() as u8, now.minute() as u8)
+}